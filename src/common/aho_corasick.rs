@@ -0,0 +1,148 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Finds every occurrence of a set of patterns, overlapping ones included,
+/// in a single pass over the text.
+#[derive(Debug, Clone)]
+pub struct AhoCorasick<V> {
+    nodes: Vec<Node>,
+    /// pattern length and value, indexed by pattern id
+    patterns: Vec<(usize, V)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    next: HashMap<u8, usize>,
+    fail: usize,
+    /// ids of the patterns ending here, including the ones reachable through `fail`
+    outputs: Vec<usize>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Match<'a, V> {
+    /// byte offset of the first matched byte
+    pub start: usize,
+    /// byte offset after the last matched byte
+    pub end: usize,
+    pub value: &'a V,
+}
+
+impl<V> AhoCorasick<V> {
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = (S, V)>) -> Self {
+        let mut nodes = vec![Node::default()];
+        let patterns: Vec<_> = patterns
+            .into_iter()
+            .enumerate()
+            .map(|(id, (pattern, value))| {
+                let pattern = pattern.as_ref();
+                assert!(!pattern.is_empty(), "patterns cannot be empty");
+                let mut current = 0;
+                for &byte in pattern.as_bytes() {
+                    current = match nodes[current].next.get(&byte) {
+                        Some(&next) => next,
+                        None => {
+                            nodes.push(Node::default());
+                            let next = nodes.len() - 1;
+                            nodes[current].next.insert(byte, next);
+                            next
+                        }
+                    };
+                }
+                nodes[current].outputs.push(id);
+                (pattern.len(), value)
+            })
+            .collect();
+
+        // breadth first, so the fail node of every parent is known before its children
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(current) = queue.pop_front() {
+            let children: Vec<_> = nodes[current]
+                .next
+                .iter()
+                .map(|(&byte, &child)| (byte, child))
+                .collect();
+            for (byte, child) in children {
+                let mut fail = nodes[current].fail;
+                let fail = loop {
+                    if let Some(&next) = nodes[fail].next.get(&byte) {
+                        break next;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = nodes[fail].fail;
+                };
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        Self { nodes, patterns }
+    }
+    /// All matches ordered by their end, longer matches first when they end together.
+    pub fn find_overlapping<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = Match<'a, V>> + 'a {
+        let mut current = 0;
+        text.bytes().enumerate().flat_map(move |(i, byte)| {
+            current = loop {
+                if let Some(&next) = self.nodes[current].next.get(&byte) {
+                    break next;
+                }
+                if current == 0 {
+                    break 0;
+                }
+                current = self.nodes[current].fail;
+            };
+            self.nodes[current].outputs.iter().map(move |&id| {
+                let (len, value) = &self.patterns[id];
+                Match {
+                    start: i + 1 - len,
+                    end: i + 1,
+                    value,
+                }
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(automaton: &AhoCorasick<u32>, text: &str) -> Vec<(usize, usize, u32)> {
+        automaton
+            .find_overlapping(text)
+            .map(|m| (m.start, m.end, *m.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let automaton = AhoCorasick::new([("one", 1), ("eight", 8), ("nine", 9), ("two", 2)]);
+        assert_eq!(vec![(0, 4, 9), (3, 8, 8)], matches(&automaton, "nineight"));
+        assert_eq!(vec![(1, 4, 2), (3, 6, 1)], matches(&automaton, "xtwone3"));
+        assert!(matches(&automaton, "tw on").is_empty());
+    }
+
+    #[test]
+    fn test_nested_patterns() {
+        let automaton = AhoCorasick::new([("he", 1), ("she", 2), ("his", 3), ("hers", 4)]);
+        assert_eq!(
+            vec![(1, 4, 2), (2, 4, 1), (2, 6, 4)],
+            matches(&automaton, "ushers")
+        );
+        let automaton = AhoCorasick::new([("seven", 7), ("seventeen", 17), ("teen", 10)]);
+        assert_eq!(
+            vec![(0, 5, 7), (0, 9, 17), (5, 9, 10)],
+            matches(&automaton, "seventeen")
+        );
+        let automaton = AhoCorasick::new([("zwölf", 12), ("elf", 11)]);
+        assert_eq!(
+            vec![(0, 6, 12), (6, 9, 11)],
+            matches(&automaton, "zwölfelf")
+        );
+    }
+}
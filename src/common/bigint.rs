@@ -0,0 +1,412 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Arbitrary precision signed integer, for when exact intermediate values outgrow `i128`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    /// little-endian limbs without trailing zeros, so zero is empty (and never negative)
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+    pub fn abs(&self) -> Self {
+        Self {
+            negative: false,
+            magnitude: self.magnitude.clone(),
+        }
+    }
+    pub fn bit_len(&self) -> u32 {
+        bit_len(&self.magnitude)
+    }
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u128, |acc, &limb| (acc << 32) | limb as u128);
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+    /// Truncating division, the remainder has the sign of `self`.
+    pub fn div_rem(&self, rhs: &Self) -> (Self, Self) {
+        assert!(!rhs.is_zero(), "division by zero");
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &rhs.magnitude);
+        (
+            Self::from_parts(self.negative != rhs.negative, quotient),
+            Self::from_parts(self.negative, remainder),
+        )
+    }
+    /// Euclidean division, the remainder is never negative.
+    pub fn div_rem_euclid(&self, rhs: &Self) -> (Self, Self) {
+        let (quotient, remainder) = self.div_rem(rhs);
+        if remainder.is_negative() {
+            if rhs.is_negative() {
+                (&quotient + &Self::from(1), &remainder - rhs)
+            } else {
+                (&quotient - &Self::from(1), &remainder + rhs)
+            }
+        } else {
+            (quotient, remainder)
+        }
+    }
+    /// Always non-negative, `gcd(0, 0) = 0`.
+    pub fn gcd(&self, rhs: &Self) -> Self {
+        Self::from_parts(false, gcd_magnitude(&self.magnitude, &rhs.magnitude))
+    }
+    fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn bit_len(magnitude: &[u32]) -> u32 {
+    magnitude.last().map_or(0, |&last| {
+        (magnitude.len() as u32 - 1) * 32 + (32 - last.leading_zeros())
+    })
+}
+
+fn cmp_magnitude(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..left.len().max(right.len()) {
+        let sum = left.get(i).copied().unwrap_or(0) as u64
+            + right.get(i).copied().unwrap_or(0) as u64
+            + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// requires `left >= right`
+fn sub_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in left.iter().enumerate() {
+        let mut diff = limb as i64 - right.get(i).copied().unwrap_or(0) as i64 - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1 << 32;
+            borrow = 1;
+        }
+        result.push(diff as u32);
+    }
+    assert_eq!(0, borrow, "magnitude subtraction underflow");
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    if left.is_empty() || right.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![0u32; left.len() + right.len()];
+    for (i, &a) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &b) in right.iter().enumerate() {
+            let current = result[i + j] as u64 + a as u64 * b as u64 + carry;
+            result[i + j] = current as u32;
+            carry = current >> 32;
+        }
+        result[i + right.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+fn shl_magnitude(magnitude: &[u32], bits: u32) -> Vec<u32> {
+    let (limbs, bits) = ((bits / 32) as usize, bits % 32);
+    let mut result = vec![0u32; limbs];
+    let mut carry = 0u32;
+    for &limb in magnitude {
+        result.push((limb << bits) | carry);
+        carry = if bits == 0 { 0 } else { limb >> (32 - bits) };
+    }
+    result.push(carry);
+    trim(&mut result);
+    result
+}
+
+fn shr_magnitude(magnitude: &[u32], bits: u32) -> Vec<u32> {
+    let (limbs, bits) = ((bits / 32) as usize, bits % 32);
+    let magnitude = magnitude.get(limbs..).unwrap_or_default();
+    let mut result: Vec<u32> = (0..magnitude.len())
+        .map(|i| {
+            let high = if bits == 0 {
+                0
+            } else {
+                magnitude.get(i + 1).map_or(0, |&next| next << (32 - bits))
+            };
+            (magnitude[i] >> bits) | high
+        })
+        .collect();
+    trim(&mut result);
+    result
+}
+
+fn trailing_zeros(magnitude: &[u32]) -> u32 {
+    let limbs = magnitude.iter().take_while(|&&limb| limb == 0).count();
+    limbs as u32 * 32 + magnitude.get(limbs).map_or(0, |limb| limb.trailing_zeros())
+}
+
+fn div_rem_magnitude(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(dividend, divisor) == Ordering::Less {
+        return (vec![], dividend.to_vec());
+    }
+    if let [divisor] = divisor {
+        let divisor = *divisor as u64;
+        let mut quotient = vec![0u32; dividend.len()];
+        let mut remainder = 0u64;
+        for (i, &limb) in dividend.iter().enumerate().rev() {
+            let current = (remainder << 32) | limb as u64;
+            quotient[i] = (current / divisor) as u32;
+            remainder = current % divisor;
+        }
+        trim(&mut quotient);
+        let mut remainder = vec![remainder as u32];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+    // binary long division
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder = vec![];
+    for bit in (0..bit_len(dividend)).rev() {
+        remainder = shl_magnitude(&remainder, 1);
+        if dividend[(bit / 32) as usize] & (1 << (bit % 32)) != 0 {
+            if remainder.is_empty() {
+                remainder.push(1);
+            } else {
+                remainder[0] |= 1;
+            }
+        }
+        if cmp_magnitude(&remainder, divisor) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, divisor);
+            quotient[(bit / 32) as usize] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+/// binary gcd, which only needs shifts and subtractions
+fn gcd_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    if left.is_empty() {
+        return right.to_vec();
+    }
+    if right.is_empty() {
+        return left.to_vec();
+    }
+    let (left_zeros, right_zeros) = (trailing_zeros(left), trailing_zeros(right));
+    let mut a = shr_magnitude(left, left_zeros);
+    let mut b = shr_magnitude(right, right_zeros);
+    loop {
+        match cmp_magnitude(&a, &b) {
+            Ordering::Equal => break,
+            Ordering::Less => std::mem::swap(&mut a, &mut b),
+            Ordering::Greater => {}
+        }
+        a = sub_magnitude(&a, &b);
+        a = shr_magnitude(&a, trailing_zeros(&a));
+    }
+    shl_magnitude(&a, left_zeros.min(right_zeros))
+}
+
+impl From<i128> for BigInt {
+    fn from(value: i128) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = vec![];
+        while magnitude > 0 {
+            limbs.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        Self::from_parts(value < 0, limbs)
+    }
+}
+
+impl From<i32> for BigInt {
+    fn from(value: i32) -> Self {
+        Self::from(value as i128)
+    }
+}
+
+impl std::ops::Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        if self.negative == rhs.negative {
+            return BigInt::from_parts(
+                self.negative,
+                add_magnitude(&self.magnitude, &rhs.magnitude),
+            );
+        }
+        match cmp_magnitude(&self.magnitude, &rhs.magnitude) {
+            Ordering::Less => {
+                BigInt::from_parts(rhs.negative, sub_magnitude(&rhs.magnitude, &self.magnitude))
+            }
+            _ => BigInt::from_parts(
+                self.negative,
+                sub_magnitude(&self.magnitude, &rhs.magnitude),
+            ),
+        }
+    }
+}
+
+impl std::ops::Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + &-rhs
+    }
+}
+
+impl std::ops::Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        BigInt::from_parts(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.magnitude, &rhs.magnitude),
+        )
+    }
+}
+
+impl std::ops::Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> Self::Output {
+        BigInt::from_parts(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        const CHUNK: u32 = 1_000_000_000;
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_magnitude(&magnitude, &[CHUNK]);
+            chunks.push(remainder.first().copied().unwrap_or(0));
+            magnitude = quotient;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(value: i128) -> BigInt {
+        BigInt::from(value)
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let values = [
+            0,
+            1,
+            -1,
+            7,
+            -13,
+            u32::MAX as i128,
+            -(u64::MAX as i128),
+            123_456_789_012_345_678_901,
+            -98_765_432_109_876_543,
+        ];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                assert_eq!(Some(a + b), (&big(a) + &big(b)).to_i128());
+                assert_eq!(Some(a - b), (&big(a) - &big(b)).to_i128());
+                assert_eq!(a.cmp(&b), big(a).cmp(&big(b)));
+                if let Some(product) = a.checked_mul(b) {
+                    assert_eq!(Some(product), (&big(a) * &big(b)).to_i128());
+                }
+                if b != 0 {
+                    let (quotient, remainder) = big(a).div_rem_euclid(&big(b));
+                    assert_eq!(Some(a.div_euclid(b)), quotient.to_i128(), "{a} / {b}");
+                    assert_eq!(Some(a.rem_euclid(b)), remainder.to_i128(), "{a} % {b}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_values() {
+        let a = &(&big(i128::MAX) * &big(i128::MAX)) * &big(-3);
+        let (quotient, remainder) = a.div_rem(&big(i128::MAX));
+        assert_eq!(&big(i128::MAX) * &big(-3), quotient);
+        assert!(remainder.is_zero());
+        assert_eq!(None, a.to_i128());
+        assert_eq!(
+            "-86844066927987146567678238756515930888931641398467607639203064183639542595587",
+            a.to_string()
+        );
+        assert_eq!(
+            &big(i128::MAX) * &big(3),
+            a.gcd(&(&big(i128::MAX) * &big(36)))
+        );
+        assert_eq!(Some(i128::MIN), big(i128::MIN).to_i128());
+    }
+}
@@ -0,0 +1,129 @@
+/// A hash map that keeps its entries in insertion order inside each bucket, with the hash
+/// function and number of buckets chosen by the caller. Replacing a value keeps its place.
+pub struct BucketMap<K, V> {
+    buckets: Vec<Vec<(K, V)>>,
+    hash: Box<dyn Fn(&K) -> u64>,
+}
+
+/// An entry with its position: `slot` is its index inside bucket `bucket`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Entry<'a, K, V> {
+    pub bucket: usize,
+    pub slot: usize,
+    pub key: &'a K,
+    pub value: &'a V,
+}
+
+impl<K: Eq, V> BucketMap<K, V> {
+    /// Keys go to bucket `hash(key) % bucket_count`.
+    pub fn new(bucket_count: usize, hash: impl Fn(&K) -> u64 + 'static) -> Self {
+        assert!(bucket_count > 0, "need at least one bucket");
+        Self {
+            buckets: (0..bucket_count).map(|_| vec![]).collect(),
+            hash: Box::new(hash),
+        }
+    }
+    fn bucket(&self, key: &K) -> usize {
+        ((self.hash)(key) % self.buckets.len() as u64) as usize
+    }
+    /// Replaces the value in place if the key is already there, returning the old value.
+    /// Otherwise adds it at the end of its bucket.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let bucket = self.bucket(&key);
+        let bucket = &mut self.buckets[bucket];
+        match bucket.iter_mut().find(|(other, _)| other == &key) {
+            Some((_, existing)) => Some(std::mem::replace(existing, value)),
+            None => {
+                bucket.push((key, value));
+                None
+            }
+        }
+    }
+    /// The entries after the removed one move up in their bucket.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let bucket = self.bucket(key);
+        let bucket = &mut self.buckets[bucket];
+        let slot = bucket.iter().position(|(other, _)| other == key)?;
+        Some(bucket.remove(slot).1)
+    }
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(|bucket| bucket.len()).sum()
+    }
+    pub fn bucket_count(&self) -> usize {
+        self.buckets.len()
+    }
+    /// Bucket by bucket, in insertion order inside each bucket.
+    pub fn iter(&self) -> impl Iterator<Item = Entry<'_, K, V>> {
+        self.buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(bucket, entries)| {
+                entries
+                    .iter()
+                    .enumerate()
+                    .map(move |(slot, (key, value))| Entry {
+                        bucket,
+                        slot,
+                        key,
+                        value,
+                    })
+            })
+    }
+    /// Sums `value` times the 1-based bucket and slot numbers of every entry.
+    pub fn weighted_sum(&self, value: impl Fn(&V) -> u64) -> u64 {
+        self.iter()
+            .map(|entry| (entry.bucket as u64 + 1) * (entry.slot as u64 + 1) * value(entry.value))
+            .sum()
+    }
+    pub fn sum(&self, value: impl Fn(&V) -> u64) -> u64 {
+        self.iter().map(|entry| value(entry.value)).sum()
+    }
+    /// How many entries the fullest bucket holds.
+    pub fn max_bucket_len(&self) -> usize {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.len())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut map = BucketMap::new(2, |&key: &u64| key);
+        assert_eq!(None, map.insert(1, "a"));
+        assert_eq!(None, map.insert(3, "b"));
+        assert_eq!(None, map.insert(2, "c"));
+        assert_eq!(Some("a"), map.insert(1, "d"));
+        let entries: Vec<_> = map
+            .iter()
+            .map(|entry| (entry.bucket, entry.slot, *entry.key, *entry.value))
+            .collect();
+        assert_eq!(
+            vec![(0, 0, 2, "c"), (1, 0, 1, "d"), (1, 1, 3, "b")],
+            entries
+        );
+
+        assert_eq!(Some("d"), map.remove(&1));
+        assert_eq!(None, map.remove(&1));
+        let moved = map.iter().find(|entry| *entry.key == 3).unwrap();
+        assert_eq!((1, 0, "b"), (moved.bucket, moved.slot, *moved.value));
+        assert_eq!(2, map.len());
+        assert_eq!(1, map.max_bucket_len());
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let mut map = BucketMap::new(3, |&key: &u64| key / 10);
+        map.insert(0, 5);
+        map.insert(1, 2);
+        map.insert(21, 4);
+        // 1 * 1 * 5 + 1 * 2 * 2 + 3 * 1 * 4
+        assert_eq!(21, map.weighted_sum(|&value| value));
+        assert_eq!(11, map.sum(|&value| value));
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+
+/// A priority queue for small integer costs, meant for pushes that never go far below the
+/// last popped cost, as in Dijkstra with non-negative weights. Keeps one bucket per cost
+/// from the cheapest pending one up, so pushing and popping are O(1) amortized.
+#[derive(Debug, Clone)]
+pub struct BucketQueue<T> {
+    buckets: VecDeque<Vec<T>>,
+    /// the cost of `buckets[0]`
+    base: u64,
+    len: usize,
+}
+
+impl<T> Default for BucketQueue<T> {
+    fn default() -> Self {
+        Self {
+            buckets: VecDeque::new(),
+            base: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> BucketQueue<T> {
+    pub fn push(&mut self, cost: u64, item: T) {
+        if self.len == 0 {
+            self.buckets.clear();
+            self.base = cost;
+        }
+        while cost < self.base {
+            self.buckets.push_front(Vec::new());
+            self.base -= 1;
+        }
+        let index = (cost - self.base) as usize;
+        if self.buckets.len() <= index {
+            self.buckets.resize_with(index + 1, Vec::new);
+        }
+        self.buckets[index].push(item);
+        self.len += 1;
+    }
+    /// One of the items with the lowest cost.
+    pub fn pop(&mut self) -> Option<(u64, T)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.buckets.front().is_some_and(|bucket| bucket.is_empty()) {
+            self.buckets.pop_front();
+            self.base += 1;
+        }
+        self.len -= 1;
+        let item = self.buckets.front_mut()?.pop()?;
+        Some((self.base, item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order() {
+        let mut queue = BucketQueue::default();
+        queue.push(5, 'a');
+        queue.push(3, 'b');
+        queue.push(9, 'c');
+        assert_eq!(Some((3, 'b')), queue.pop());
+        queue.push(4, 'd');
+        assert_eq!(Some((4, 'd')), queue.pop());
+        assert_eq!(Some((5, 'a')), queue.pop());
+        assert_eq!(Some((9, 'c')), queue.pop());
+        assert_eq!(None, queue.pop());
+        queue.push(1, 'e');
+        assert_eq!(Some((1, 'e')), queue.pop());
+    }
+}
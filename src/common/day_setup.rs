@@ -53,6 +53,7 @@ pub struct AppContext {
     testing: Option<usize>,
     text_input: Option<Box<InputProvider>>,
     testing_inputs: Vec<Box<InputProvider>>,
+    args: Vec<String>,
 }
 
 impl AppContext {
@@ -71,6 +72,13 @@ impl AppContext {
     pub fn is_testing(&self) -> bool {
         self.testing.is_some()
     }
+    /// Extra command line arguments after the day number.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
 
     pub fn get_input(&self) -> TextInput {
         if let Some(testing) = self.testing {
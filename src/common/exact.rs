@@ -0,0 +1,463 @@
+use crate::common::bigint::BigInt;
+use crate::common::models::{Point, Point3};
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// The integer operations [Rational] needs, implemented for `i128` and [BigInt].
+pub trait Integer: Clone + Eq + Ord + Hash + Debug + Display + From<i128> {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn is_zero(&self) -> bool;
+    fn is_negative(&self) -> bool;
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    fn checked_neg(&self) -> Option<Self>;
+    /// euclidean division: the remainder is never negative
+    fn div_rem_euclid(&self, rhs: &Self) -> (Self, Self);
+    /// non-negative greatest common divisor, `None` if it is not representable
+    fn gcd(&self, rhs: &Self) -> Option<Self>;
+    /// number of bits of the absolute value
+    fn bit_len(&self) -> u32;
+    fn to_i128(&self) -> Option<i128>;
+}
+
+impl Integer for i128 {
+    fn zero() -> Self {
+        0
+    }
+    fn one() -> Self {
+        1
+    }
+    fn is_zero(&self) -> bool {
+        *self == 0
+    }
+    fn is_negative(&self) -> bool {
+        *self < 0
+    }
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        i128::checked_add(*self, *rhs)
+    }
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        i128::checked_mul(*self, *rhs)
+    }
+    fn checked_neg(&self) -> Option<Self> {
+        i128::checked_neg(*self)
+    }
+    fn div_rem_euclid(&self, rhs: &Self) -> (Self, Self) {
+        (self.div_euclid(*rhs), self.rem_euclid(*rhs))
+    }
+    fn gcd(&self, rhs: &Self) -> Option<Self> {
+        let (mut a, mut b) = (self.unsigned_abs(), rhs.unsigned_abs());
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        i128::try_from(a).ok()
+    }
+    fn bit_len(&self) -> u32 {
+        128 - self.unsigned_abs().leading_zeros()
+    }
+    fn to_i128(&self) -> Option<i128> {
+        Some(*self)
+    }
+}
+
+impl Integer for BigInt {
+    fn zero() -> Self {
+        BigInt::zero()
+    }
+    fn one() -> Self {
+        BigInt::from(1)
+    }
+    fn is_zero(&self) -> bool {
+        BigInt::is_zero(self)
+    }
+    fn is_negative(&self) -> bool {
+        BigInt::is_negative(self)
+    }
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+    fn checked_neg(&self) -> Option<Self> {
+        Some(-self)
+    }
+    fn div_rem_euclid(&self, rhs: &Self) -> (Self, Self) {
+        BigInt::div_rem_euclid(self, rhs)
+    }
+    fn gcd(&self, rhs: &Self) -> Option<Self> {
+        Some(BigInt::gcd(self, rhs))
+    }
+    fn bit_len(&self) -> u32 {
+        BigInt::bit_len(self)
+    }
+    fn to_i128(&self) -> Option<i128> {
+        BigInt::to_i128(self)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExactError {
+    Overflow,
+    DivisionByZero,
+    /// the result is not an integer
+    Inexact(String),
+    /// parallel lines or a singular system: no unique solution
+    Degenerate,
+    /// the equations contradict each other: no solution at all
+    Inconsistent,
+}
+
+impl Display for ExactError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExactError::Overflow => write!(f, "arithmetic overflow"),
+            ExactError::DivisionByZero => write!(f, "division by zero"),
+            ExactError::Inexact(value) => write!(f, "inexact result {}", value),
+            ExactError::Degenerate => write!(f, "degenerate system - no unique solution"),
+            ExactError::Inconsistent => write!(f, "inconsistent system - no solution"),
+        }
+    }
+}
+
+impl std::error::Error for ExactError {}
+
+/// Always kept reduced, with a positive denominator.
+/// `Rational<i128>` reports overflow as an error, `Rational<BigInt>` never overflows.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Rational<T = i128> {
+    numerator: T,
+    denominator: T,
+}
+
+impl<T: Integer> Rational<T> {
+    pub fn new(numerator: T, denominator: T) -> Result<Self, ExactError> {
+        if denominator.is_zero() {
+            return Err(ExactError::DivisionByZero);
+        }
+        let gcd = numerator.gcd(&denominator).ok_or(ExactError::Overflow)?;
+        let (mut numerator, mut denominator) = (
+            numerator.div_rem_euclid(&gcd).0,
+            denominator.div_rem_euclid(&gcd).0,
+        );
+        if denominator.is_negative() {
+            numerator = numerator.checked_neg().ok_or(ExactError::Overflow)?;
+            denominator = denominator.checked_neg().ok_or(ExactError::Overflow)?;
+        }
+        Ok(Self {
+            numerator,
+            denominator,
+        })
+    }
+    pub fn integer(value: T) -> Self {
+        Self {
+            numerator: value,
+            denominator: T::one(),
+        }
+    }
+    pub fn zero() -> Self {
+        Self::integer(T::zero())
+    }
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+    pub fn is_negative(&self) -> bool {
+        self.numerator.is_negative()
+    }
+    pub fn is_integer(&self) -> bool {
+        self.denominator == T::one()
+    }
+    /// bits of max(|numerator|, denominator): a measure of how "complex" the fraction is
+    pub fn height(&self) -> u32 {
+        self.numerator.bit_len().max(self.denominator.bit_len())
+    }
+    pub fn to_integer(&self) -> Result<T, ExactError> {
+        if self.is_integer() {
+            Ok(self.numerator.clone())
+        } else {
+            Err(ExactError::Inexact(self.to_string()))
+        }
+    }
+    pub fn checked_add(&self, rhs: &Self) -> Result<Self, ExactError> {
+        let overflow = || ExactError::Overflow;
+        let gcd = self
+            .denominator
+            .gcd(&rhs.denominator)
+            .ok_or_else(overflow)?;
+        let left_factor = rhs.denominator.div_rem_euclid(&gcd).0;
+        let right_factor = self.denominator.div_rem_euclid(&gcd).0;
+        let left = self
+            .numerator
+            .checked_mul(&left_factor)
+            .ok_or_else(overflow)?;
+        let right = rhs
+            .numerator
+            .checked_mul(&right_factor)
+            .ok_or_else(overflow)?;
+        Self::new(
+            left.checked_add(&right).ok_or_else(overflow)?,
+            right_factor
+                .checked_mul(&rhs.denominator)
+                .ok_or_else(overflow)?,
+        )
+    }
+    pub fn checked_sub(&self, rhs: &Self) -> Result<Self, ExactError> {
+        self.checked_add(&rhs.checked_neg()?)
+    }
+    pub fn checked_mul(&self, rhs: &Self) -> Result<Self, ExactError> {
+        if self.is_zero() || rhs.is_zero() {
+            return Ok(Self::zero());
+        }
+        let overflow = || ExactError::Overflow;
+        let gcd_left = self.numerator.gcd(&rhs.denominator).ok_or_else(overflow)?;
+        let gcd_right = rhs.numerator.gcd(&self.denominator).ok_or_else(overflow)?;
+        let numerator_left = self.numerator.div_rem_euclid(&gcd_left).0;
+        let numerator_right = rhs.numerator.div_rem_euclid(&gcd_right).0;
+        let denominator_left = self.denominator.div_rem_euclid(&gcd_right).0;
+        let denominator_right = rhs.denominator.div_rem_euclid(&gcd_left).0;
+        Self::new(
+            numerator_left
+                .checked_mul(&numerator_right)
+                .ok_or_else(overflow)?,
+            denominator_left
+                .checked_mul(&denominator_right)
+                .ok_or_else(overflow)?,
+        )
+    }
+    pub fn checked_div(&self, rhs: &Self) -> Result<Self, ExactError> {
+        self.checked_mul(&rhs.recip()?)
+    }
+    pub fn checked_neg(&self) -> Result<Self, ExactError> {
+        Ok(Self {
+            numerator: self.numerator.checked_neg().ok_or(ExactError::Overflow)?,
+            denominator: self.denominator.clone(),
+        })
+    }
+    pub fn recip(&self) -> Result<Self, ExactError> {
+        Self::new(self.denominator.clone(), self.numerator.clone())
+    }
+}
+
+/// compares a/b with c/d (b, d > 0) without multiplying, so it cannot overflow
+fn cmp_fractions<T: Integer>(a: &T, b: &T, c: &T, d: &T) -> Ordering {
+    let (quotient_left, remainder_left) = a.div_rem_euclid(b);
+    let (quotient_right, remainder_right) = c.div_rem_euclid(d);
+    if quotient_left != quotient_right {
+        return quotient_left.cmp(&quotient_right);
+    }
+    match (remainder_left.is_zero(), remainder_right.is_zero()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // ra/b < rc/d <=> d/rc < b/ra
+        (false, false) => cmp_fractions(d, &remainder_right, b, &remainder_left),
+    }
+}
+
+impl<T: Integer> Ord for Rational<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_fractions(
+            &self.numerator,
+            &self.denominator,
+            &other.numerator,
+            &other.denominator,
+        )
+    }
+}
+
+impl<T: Integer> PartialOrd for Rational<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Integer> Default for Rational<T> {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl<T: Integer> Add for Rational<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(&rhs).expect("rational overflow")
+    }
+}
+
+impl<T: Integer> Sub for Rational<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(&rhs).expect("rational overflow")
+    }
+}
+
+impl<T: Integer> Mul for Rational<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(&rhs).expect("rational overflow")
+    }
+}
+
+impl<T: Integer> Div for Rational<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(&rhs).expect("rational division")
+    }
+}
+
+impl<T: Integer> Neg for Rational<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.checked_neg().expect("rational overflow")
+    }
+}
+
+impl<T: Integer> Display for Rational<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+/// A line through `point` going along `direction`, parametrised as `point + t * direction`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Line {
+    pub point: Point3<i128>,
+    pub direction: Point3<i128>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LineIntersection {
+    /// parameter along the first line
+    pub t_self: Rational,
+    /// parameter along the second line
+    pub t_other: Rational,
+}
+
+impl Line {
+    pub fn at_xy(&self, t: Rational) -> Result<Point<Rational>, ExactError> {
+        Ok(Point {
+            x: Self::coordinate(t, self.point.x, self.direction.x)?,
+            y: Self::coordinate(t, self.point.y, self.direction.y)?,
+        })
+    }
+    /// Intersects the projections of both lines onto the xy plane.
+    pub fn intersect_xy(&self, other: &Self) -> Result<LineIntersection, ExactError> {
+        let determinant = |a: i128, b: i128, c: i128, d: i128| {
+            a.checked_mul(d)
+                .zip(b.checked_mul(c))
+                .and_then(|(ad, bc)| ad.checked_sub(bc))
+                .ok_or(ExactError::Overflow)
+        };
+        // point_self + t_self * direction_self = point_other + t_other * direction_other,
+        // solved with Cramer's rule on integers so only the two quotients get reduced
+        let overflow = |value: Option<i128>| value.ok_or(ExactError::Overflow);
+        let (a, c) = (self.direction.x, self.direction.y);
+        let b = overflow(other.direction.x.checked_neg())?;
+        let d = overflow(other.direction.y.checked_neg())?;
+        let e = overflow(other.point.x.checked_sub(self.point.x))?;
+        let f = overflow(other.point.y.checked_sub(self.point.y))?;
+        let det = determinant(a, b, c, d)?;
+        if det == 0 {
+            return Err(ExactError::Degenerate);
+        }
+        Ok(LineIntersection {
+            t_self: Rational::new(determinant(e, b, f, d)?, det)?,
+            t_other: Rational::new(determinant(a, e, c, f)?, det)?,
+        })
+    }
+    /// `point + t * direction` as a single fraction, so it is only reduced once
+    fn coordinate(t: Rational, point: i128, direction: i128) -> Result<Rational, ExactError> {
+        let numerator = point
+            .checked_mul(t.denominator)
+            .zip(t.numerator.checked_mul(direction))
+            .and_then(|(point, offset)| point.checked_add(offset))
+            .ok_or(ExactError::Overflow)?;
+        Rational::new(numerator, t.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(numerator: i128, denominator: i128) -> Rational {
+        Rational::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn test_rational_arithmetic() {
+        assert_eq!(r(1, 2), r(2, 4));
+        assert_eq!(r(-1, 2), r(1, -2));
+        assert_eq!(r(5, 6), r(1, 2) + r(1, 3));
+        assert_eq!(r(1, 6), r(1, 2) - r(1, 3));
+        assert_eq!(r(1, 6), r(1, 2) * r(1, 3));
+        assert_eq!(r(3, 2), r(1, 2) / r(1, 3));
+        assert!(r(1, 3) < r(1, 2));
+        assert!(r(-1, 2) < r(-1, 3));
+        assert!(r(i128::MAX, i128::MAX - 1) < r(i128::MAX - 1, i128::MAX - 2));
+        assert_eq!(
+            Err(ExactError::Inexact("7/2".to_string())),
+            r(7, 2).to_integer()
+        );
+        assert_eq!(Ok(4), r(8, 2).to_integer());
+        assert_eq!(
+            Err(ExactError::Overflow),
+            Rational::integer(i128::MAX).checked_add(&Rational::integer(1))
+        );
+        assert_eq!(Err(ExactError::DivisionByZero), Rational::new(1, 0));
+    }
+
+    #[test]
+    fn test_big_rational() {
+        let big = |value: i128| Rational::integer(BigInt::from(value));
+        let huge = big(i128::MAX) * big(i128::MAX);
+        assert_eq!(big(i128::MAX), huge.clone() / big(i128::MAX));
+        let third = (huge.clone() + big(1)) / big(3);
+        assert!(!third.is_integer());
+        assert!(third > huge / big(3));
+        assert_eq!(Ok(BigInt::from(7)), (big(14) / big(2)).to_integer());
+    }
+
+    #[test]
+    fn test_line_intersection() {
+        let line = |point: [i128; 3], direction: [i128; 3]| Line {
+            point: Point3 {
+                x: point[0],
+                y: point[1],
+                z: point[2],
+            },
+            direction: Point3 {
+                x: direction[0],
+                y: direction[1],
+                z: direction[2],
+            },
+        };
+        let a = line([19, 13, 30], [-2, 1, -2]);
+        let b = line([18, 19, 22], [-1, -1, -2]);
+        let intersection = a.intersect_xy(&b).unwrap();
+        let point = a.at_xy(intersection.t_self).unwrap();
+        assert_eq!(
+            Point {
+                x: r(43, 3),
+                y: r(46, 3)
+            },
+            point
+        );
+        assert_eq!(point, b.at_xy(intersection.t_other).unwrap());
+
+        let parallel = line([18, 19, 22], [-2, 1, 0]);
+        assert_eq!(Err(ExactError::Degenerate), a.intersect_xy(&parallel));
+    }
+}
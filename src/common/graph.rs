@@ -0,0 +1,102 @@
+/// Strongly connected components of a directed graph given as adjacency lists.
+/// Returns the component of every node. Components are numbered in topological order:
+/// edges between different components always go from a lower to a higher number.
+pub fn strongly_connected_components(adjacency: &[Vec<usize>]) -> Vec<usize> {
+    // Kosaraju: order the nodes by DFS finish time, then collect components
+    // on the reversed graph starting from the last finished node.
+    let mut finished = Vec::with_capacity(adjacency.len());
+    let mut visited = vec![false; adjacency.len()];
+    for root in 0..adjacency.len() {
+        if visited[root] {
+            continue;
+        }
+        visited[root] = true;
+        let mut stack = vec![(root, 0)];
+        while let Some((node, next_edge)) = stack.last_mut() {
+            if let Some(&next) = adjacency[*node].get(*next_edge) {
+                *next_edge += 1;
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push((next, 0));
+                }
+            } else {
+                finished.push(*node);
+                stack.pop();
+            }
+        }
+    }
+
+    let mut reversed = vec![vec![]; adjacency.len()];
+    for (node, edges) in adjacency.iter().enumerate() {
+        for &next in edges {
+            reversed[next].push(node);
+        }
+    }
+
+    let mut components = vec![usize::MAX; adjacency.len()];
+    let mut count = 0;
+    for &root in finished.iter().rev() {
+        if components[root] != usize::MAX {
+            continue;
+        }
+        components[root] = count;
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for &previous in reversed[node].iter() {
+                if components[previous] == usize::MAX {
+                    components[previous] = count;
+                    stack.push(previous);
+                }
+            }
+        }
+        count += 1;
+    }
+    components
+}
+
+/// Which nodes can be reached from any of `starts`, the starts included.
+pub fn reachable(adjacency: &[Vec<usize>], starts: impl IntoIterator<Item = usize>) -> Vec<bool> {
+    let mut visited = vec![false; adjacency.len()];
+    let mut stack: Vec<_> = starts.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        if std::mem::replace(&mut visited[node], true) {
+            continue;
+        }
+        stack.extend(adjacency[node].iter().filter(|&&next| !visited[next]));
+    }
+    visited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strongly_connected_components() {
+        // 0 <-> 1 -> 2 <-> 3 -> 4, 5 on its own with a self loop
+        let adjacency = vec![vec![1], vec![0, 2], vec![3], vec![2, 4], vec![], vec![5]];
+        let components = strongly_connected_components(&adjacency);
+        assert_eq!(components[0], components[1]);
+        assert_eq!(components[2], components[3]);
+        assert_ne!(components[1], components[2]);
+        assert_eq!(4, components.iter().max().unwrap() + 1);
+        for (node, edges) in adjacency.iter().enumerate() {
+            for &next in edges {
+                assert!(components[node] <= components[next]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reachable() {
+        let adjacency = vec![vec![1], vec![0, 2], vec![3], vec![2, 4], vec![], vec![5]];
+        assert_eq!(
+            vec![false, false, true, true, true, false],
+            reachable(&adjacency, [2])
+        );
+        assert_eq!(
+            vec![true, true, true, true, true, true],
+            reachable(&adjacency, [0, 5])
+        );
+    }
+}
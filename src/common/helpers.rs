@@ -23,6 +23,63 @@ pub fn greatest_common_denominator(left: u64, right: u64) -> u64 {
     greatest_common_denominator(min, max % min)
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CongruenceError {
+    /// the two congruences contradict each other
+    NoSolution,
+    /// the combined congruence does not fit in i128
+    Overflow,
+}
+
+impl std::fmt::Display for CongruenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CongruenceError::NoSolution => write!(f, "the congruences have no common solution"),
+            CongruenceError::Overflow => write!(f, "the combined congruence overflows i128"),
+        }
+    }
+}
+
+impl std::error::Error for CongruenceError {}
+
+/// Combines `x = a.0 (mod a.1)` and `x = b.0 (mod b.1)` into a single congruence
+/// `x = r (mod lcm)`, with `0 <= r < lcm`.
+pub fn chinese_remainder(
+    a: (i128, i128),
+    b: (i128, i128),
+) -> Result<(i128, i128), CongruenceError> {
+    let ((a_rem, a_mod), (b_rem, b_mod)) = (a, b);
+    let (gcd, p, _) = extended_gcd(a_mod, b_mod);
+    let diff = b_rem.checked_sub(a_rem).ok_or(CongruenceError::Overflow)?;
+    if diff % gcd != 0 {
+        return Err(CongruenceError::NoSolution);
+    }
+    let overflow = || CongruenceError::Overflow;
+    let lcm = (a_mod / gcd).checked_mul(b_mod).ok_or_else(overflow)?;
+    // a_rem + a_mod * k solves both for k = p * (b_rem - a_rem) / gcd (mod b_mod / gcd)
+    let step = b_mod / gcd;
+    let k = (p % step)
+        .checked_mul((diff / gcd) % step)
+        .ok_or_else(overflow)?
+        .rem_euclid(step);
+    let rem = a_mod
+        .checked_mul(k)
+        .and_then(|offset| a_rem.checked_add(offset))
+        .ok_or_else(overflow)?
+        .rem_euclid(lcm);
+    Ok((rem, lcm))
+}
+
+/// `(gcd, x, y)` with `a * x + b * y = gcd`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (gcd, x, y) = extended_gcd(b, a % b);
+        (gcd, y, x - (a / b) * y)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,4 +120,24 @@ mod tests {
         assert_eq!(0, least_common_multiple(0, 5));
         assert_eq!(0, least_common_multiple(0, 0));
     }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(
+            Ok((23, 105)),
+            chinese_remainder((2, 3), (3, 5)).and_then(|x| chinese_remainder(x, (2, 7)))
+        );
+        assert_eq!(Ok((10, 12)), chinese_remainder((4, 6), (2, 4)));
+        assert_eq!(
+            Err(CongruenceError::NoSolution),
+            chinese_remainder((1, 6), (2, 4))
+        );
+        assert_eq!(Ok((3, 5)), chinese_remainder((3, 5), (0, 1)));
+        assert_eq!(Ok((5, 7)), chinese_remainder((12, 7), (12, 7)));
+        let big = (1i128 << 100) + 1;
+        assert_eq!(
+            Err(CongruenceError::Overflow),
+            chinese_remainder((1, big), (2, big + 2))
+        );
+    }
 }
@@ -0,0 +1,134 @@
+use crate::common::exact::{ExactError, Integer, Rational};
+
+/// The row-reduced echelon form of an augmented system `A x = b`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReducedSystem<T = i128> {
+    rows: Vec<Vec<Rational<T>>>,
+    unknowns: usize,
+    /// column of the leading one for each of the first `rank` rows
+    pivots: Vec<usize>,
+    consistent: bool,
+}
+
+impl<T: Integer> ReducedSystem<T> {
+    /// Runs Gauss-Jordan elimination over `matrix` augmented with `rhs`.
+    pub fn new(matrix: &[Vec<Rational<T>>], rhs: &[Rational<T>]) -> Result<Self, ExactError> {
+        assert_eq!(matrix.len(), rhs.len(), "one rhs value per equation");
+        let unknowns = matrix.first().map_or(0, |row| row.len());
+        assert!(
+            matrix.iter().all(|row| row.len() == unknowns),
+            "All rows must be the same length"
+        );
+        let mut rows: Vec<Vec<Rational<T>>> = matrix
+            .iter()
+            .zip(rhs)
+            .map(|(row, rhs)| row.iter().chain([rhs]).cloned().collect())
+            .collect();
+
+        let mut pivots = Vec::new();
+        for column in 0..unknowns {
+            let row = pivots.len();
+            // the smallest pivot keeps the intermediate fractions small
+            let Some(pivot_row) = (row..rows.len())
+                .filter(|&i| !rows[i][column].is_zero())
+                .min_by_key(|&i| rows[i][column].height())
+            else {
+                continue;
+            };
+            rows.swap(row, pivot_row);
+
+            let pivot = rows[row][column].clone();
+            for value in rows[row].iter_mut() {
+                *value = value.checked_div(&pivot)?;
+            }
+            let pivot_row = rows[row].clone();
+            for (i, other) in rows.iter_mut().enumerate() {
+                let factor = other[column].clone();
+                if i == row || factor.is_zero() {
+                    continue;
+                }
+                for (value, pivot_value) in other.iter_mut().zip(pivot_row.iter()).skip(column) {
+                    *value = value.checked_sub(&factor.checked_mul(pivot_value)?)?;
+                }
+            }
+            pivots.push(column);
+        }
+
+        let consistent = rows
+            .iter()
+            .skip(pivots.len())
+            .all(|row| row[unknowns].is_zero());
+
+        Ok(Self {
+            rows,
+            unknowns,
+            pivots,
+            consistent,
+        })
+    }
+    pub fn rank(&self) -> usize {
+        self.pivots.len()
+    }
+    pub fn is_consistent(&self) -> bool {
+        self.consistent
+    }
+    /// The solution if there is exactly one.
+    pub fn unique_solution(&self) -> Result<Vec<Rational<T>>, ExactError> {
+        if !self.consistent {
+            return Err(ExactError::Inconsistent);
+        }
+        if self.rank() < self.unknowns {
+            return Err(ExactError::Degenerate);
+        }
+        Ok(self.rows[..self.unknowns]
+            .iter()
+            .map(|row| row[self.unknowns].clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows<const N: usize>(values: &[[i128; N]]) -> Vec<Vec<Rational>> {
+        values
+            .iter()
+            .map(|row| row.iter().map(|&v| Rational::integer(v)).collect())
+            .collect()
+    }
+
+    fn column(values: &[i128]) -> Vec<Rational> {
+        values.iter().map(|&v| Rational::integer(v)).collect()
+    }
+
+    #[test]
+    fn test_unique_solution() {
+        let matrix = rows(&[[2, 1, -1], [-3, -1, 2], [-2, 1, 2]]);
+        let system = ReducedSystem::new(&matrix, &column(&[8, -11, -3])).unwrap();
+        assert_eq!(3, system.rank());
+        assert_eq!(Ok(column(&[2, 3, -1])), system.unique_solution());
+    }
+
+    #[test]
+    fn test_overdetermined() {
+        let matrix = rows(&[[1, 1], [1, -1], [2, 1], [0, 3]]);
+        let system = ReducedSystem::new(&matrix, &column(&[3, 1, 5, 3])).unwrap();
+        assert_eq!(2, system.rank());
+        assert!(system.is_consistent());
+        assert_eq!(Ok(column(&[2, 1])), system.unique_solution());
+
+        let system = ReducedSystem::new(&matrix, &column(&[3, 1, 5, 4])).unwrap();
+        assert!(!system.is_consistent());
+        assert_eq!(Err(ExactError::Inconsistent), system.unique_solution());
+    }
+
+    #[test]
+    fn test_underdetermined() {
+        let matrix = rows(&[[1, 2, 3], [2, 4, 6]]);
+        let system = ReducedSystem::new(&matrix, &column(&[1, 2])).unwrap();
+        assert_eq!(1, system.rank());
+        assert!(system.is_consistent());
+        assert_eq!(Err(ExactError::Degenerate), system.unique_solution());
+    }
+}
@@ -1,3 +1,14 @@
+pub mod aho_corasick;
+pub mod bigint;
+pub mod bucket_map;
+pub mod bucket_queue;
 pub mod day_setup;
+pub mod exact;
+pub mod graph;
 pub mod helpers;
+pub mod linear;
 pub mod models;
+pub mod nonogram;
+pub mod polygon;
+pub mod random;
+pub mod sequence;
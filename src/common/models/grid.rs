@@ -134,13 +134,6 @@ impl<T> Grid<T> {
         );
         Ok(Self { grid, len_x, len_y })
     }
-    pub fn swap(&mut self, left: &Point<usize>, right: &Point<usize>) {
-        let left: *mut T = &mut self[left];
-        let right: *mut T = &mut self[right];
-        unsafe {
-            std::ptr::swap(left, right);
-        }
-    }
 }
 
 impl<T> GridLike for Grid<T> {
@@ -534,27 +527,4 @@ mod tests {
             assert_eq!(left as char, right);
         }
     }
-
-    #[test]
-    fn test_swap() {
-        let mut grid = Grid::from_iter(
-            [
-                [1, 2, 3].into_iter(),
-                [4, 5, 6].into_iter(),
-                [7, 8, 9].into_iter(),
-            ]
-            .into_iter(),
-        );
-        let left = Point { x: 1, y: 0 };
-        let right = Point { x: 0, y: 2 };
-        let left_val = grid[&left];
-        let right_val = grid[&right];
-        println!("[0][1] = {}", grid[&left]);
-        println!("[2][0] = {}", grid[&right]);
-        grid.swap(&left, &right);
-        println!("[0][1] = {}", grid[&left]);
-        println!("[2][0] = {}", grid[&right]);
-        assert_eq!(left_val, grid[&right]);
-        assert_eq!(right_val, grid[&left]);
-    }
 }
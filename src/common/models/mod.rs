@@ -1,7 +1,9 @@
 pub mod directions;
 pub mod grid;
 pub mod point;
+pub mod point3;
 
 pub use directions::{Direction, DirectionFlag};
 pub use grid::Grid;
 pub use point::Point;
+pub use point3::{Axis, Direction3, Point3, Segment3};
@@ -48,9 +48,6 @@ impl Point<usize> {
     pub fn move_in(&self, direction: Direction) -> Option<Self> {
         self.move_in_times(direction, 1)
     }
-    pub fn manhattan_distance(&self, other: &Self) -> usize {
-        (self.x.max(other.x) - self.x.min(other.x)) + (self.y.max(other.y) - self.y.min(other.y))
-    }
 }
 
 impl<T: Display> Display for Point<T> {
@@ -0,0 +1,281 @@
+use crate::common::models::Point;
+use anyhow::Context;
+use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Point3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Axis {
+    #[default]
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const fn all() -> [Axis; 3] {
+        [Self::X, Self::Y, Self::Z]
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Direction3 {
+    pub axis: Axis,
+    pub negative: bool,
+}
+
+impl<T> Point3<T> {
+    pub fn map<V>(self, mut f: impl FnMut(T) -> V) -> Point3<V> {
+        Point3 {
+            x: f(self.x),
+            y: f(self.y),
+            z: f(self.z),
+        }
+    }
+    pub fn with_z(point: Point<T>, z: T) -> Self {
+        Self {
+            x: point.x,
+            y: point.y,
+            z,
+        }
+    }
+    pub fn xy(self) -> Point<T> {
+        Point {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+impl<T: AddAssign + SubAssign> Point3<T> {
+    pub fn translate(mut self, size: T, direction: Direction3) -> Self {
+        if direction.negative {
+            self[direction.axis] -= size;
+        } else {
+            self[direction.axis] += size;
+        }
+        self
+    }
+}
+
+impl<T> Point3<T>
+where
+    T: Add<T, Output = T> + Sub<T, Output = T> + Mul<T, Output = T> + Copy,
+{
+    pub fn cross(&self, other: &Self) -> Self {
+        Point3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+}
+
+impl<T> Index<Axis> for Point3<T> {
+    type Output = T;
+
+    fn index(&self, index: Axis) -> &Self::Output {
+        match index {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+}
+
+impl<T> IndexMut<Axis> for Point3<T> {
+    fn index_mut(&mut self, index: Axis) -> &mut Self::Output {
+        match index {
+            Axis::X => &mut self.x,
+            Axis::Y => &mut self.y,
+            Axis::Z => &mut self.z,
+        }
+    }
+}
+
+impl<T> Add<Point3<T>> for Point3<T>
+where
+    T: Add<T, Output = T>,
+{
+    type Output = Point3<T>;
+
+    fn add(self, rhs: Point3<T>) -> Self::Output {
+        Point3 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl<T> Sub<Point3<T>> for Point3<T>
+where
+    T: Sub<T, Output = T>,
+{
+    type Output = Point3<T>;
+
+    fn sub(self, rhs: Point3<T>) -> Self::Output {
+        Point3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl<T, V> Mul<V> for Point3<T>
+where
+    T: Mul<V, Output = T>,
+    V: Copy,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: V) -> Self::Output {
+        Point3 {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+impl<T, V> Div<V> for Point3<T>
+where
+    T: Div<V, Output = T>,
+    V: Copy,
+{
+    type Output = Self;
+
+    fn div(self, rhs: V) -> Self::Output {
+        Point3 {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+        }
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Point3<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.map(|v| -v)
+    }
+}
+
+impl<T: Default> From<Point<T>> for Point3<T> {
+    fn from(value: Point<T>) -> Self {
+        Self::with_z(value, T::default())
+    }
+}
+
+impl<T> From<Point3<T>> for Point<T> {
+    fn from(value: Point3<T>) -> Self {
+        value.xy()
+    }
+}
+
+impl<T: Display> Display for Point3<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}, {}", self.x, self.y, self.z)
+    }
+}
+
+impl<T> FromStr for Point3<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let result = Self {
+            x: parts.next().context("no x")?.trim().parse()?,
+            y: parts.next().context("no y")?.trim().parse()?,
+            z: parts.next().context("no z")?.trim().parse()?,
+        };
+        if parts.next().is_some() {
+            return Err(anyhow::anyhow!("too many coordinates in '{}'", s));
+        }
+        Ok(result)
+    }
+}
+
+/// Axis-aligned segment between two inclusive end points that differ on at most one axis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Segment3<T> {
+    start: Point3<T>,
+    end: Point3<T>,
+    axis: Axis,
+}
+
+impl<T: PartialOrd + Copy> Segment3<T> {
+    pub fn new(a: Point3<T>, b: Point3<T>) -> anyhow::Result<Self> {
+        let mut axes = Axis::all().into_iter().filter(|&axis| a[axis] != b[axis]);
+        let axis = axes.next().unwrap_or_default();
+        if axes.next().is_some() {
+            return Err(anyhow::anyhow!("segment is not axis-aligned"));
+        }
+        let (start, end) = if a[axis] <= b[axis] { (a, b) } else { (b, a) };
+        Ok(Self { start, end, axis })
+    }
+    pub fn start(&self) -> &Point3<T> {
+        &self.start
+    }
+    pub fn axis(&self) -> Axis {
+        self.axis
+    }
+}
+
+impl<T: Sub<T, Output = T> + Copy> Segment3<T> {
+    pub fn len(&self) -> T {
+        self.end[self.axis] - self.start[self.axis]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p<T>(x: T, y: T, z: T) -> Point3<T> {
+        Point3 { x, y, z }
+    }
+
+    #[test]
+    fn test_cross() {
+        let a = p(1, 0, 0);
+        let b = p(0, 1, 0);
+        assert_eq!(p(0, 0, 1), a.cross(&b));
+        assert_eq!(p(2, 4, 6), p(1, 2, 3) * 2);
+        assert_eq!(p(-1, -2, -3), -p(1, 2, 3));
+    }
+
+    #[test]
+    fn test_point_conversions() {
+        let point = Point { x: 3, y: 4 };
+        let point3: Point3<i32> = point.into();
+        assert_eq!(p(3, 4, 0), point3);
+        let back: Point<i32> = p(3, 4, 9).into();
+        assert_eq!(point, back);
+        assert_eq!(p(1u32, 2, 3), "1, 2,3".parse().unwrap());
+    }
+
+    #[test]
+    fn test_segment() {
+        let a = Segment3::new(p(4, 1, 1), p(0, 1, 1)).unwrap();
+        let b = Segment3::new(p(2, 0, 1), p(2, 3, 1)).unwrap();
+        assert_eq!(4, a.len());
+        assert_eq!(p(0, 1, 1), *a.start());
+        assert_eq!(Axis::Y, b.axis());
+        assert_eq!(0, Segment3::new(p(1, 1, 1), p(1, 1, 1)).unwrap().len());
+        assert!(Segment3::new(p(0, 0, 0), p(1, 1, 0)).is_err());
+    }
+}
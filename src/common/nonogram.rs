@@ -0,0 +1,408 @@
+use crate::common::models::grid::GridLike;
+use crate::common::models::{Grid, Point};
+use crate::common::random::Rng;
+use std::str::FromStr;
+
+/// Counts the ways to fill one nonogram line, with a DP over (cell, group) positions.
+/// Cells are known filled (`Some(true)`), known empty (`Some(false)`) or unknown;
+/// `groups` are the lengths of the runs of filled cells, in order.
+pub struct LineSolver<'a> {
+    cells: &'a [Option<bool>],
+    groups: &'a [usize],
+    /// `ways[i * (groups + 1) + g]`: ways to fill the cells from `i` with the groups from `g`
+    ways: Vec<u128>,
+    /// `empty_before[i]`: cells known to be empty before `i`
+    empty_before: Vec<usize>,
+}
+
+/// A step of an arrangement: leave the cell empty, or place the next group starting there.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Step {
+    Empty,
+    Group,
+}
+
+impl<'a> LineSolver<'a> {
+    pub fn new(cells: &'a [Option<bool>], groups: &'a [usize]) -> Self {
+        let mut empty_before = vec![0; cells.len() + 1];
+        for (i, &cell) in cells.iter().enumerate() {
+            empty_before[i + 1] = empty_before[i] + usize::from(cell == Some(false));
+        }
+        let mut solver = Self {
+            cells,
+            groups,
+            ways: vec![0; (cells.len() + 1) * (groups.len() + 1)],
+            empty_before,
+        };
+        let end = solver.index(cells.len(), groups.len());
+        solver.ways[end] = 1;
+        for i in (0..cells.len()).rev() {
+            for g in 0..=groups.len() {
+                let ways = [Step::Empty, Step::Group]
+                    .into_iter()
+                    .filter_map(|step| solver.next(i, g, step))
+                    .map(|(i, g)| solver.ways[solver.index(i, g)])
+                    .sum();
+                let index = solver.index(i, g);
+                solver.ways[index] = ways;
+            }
+        }
+        solver
+    }
+    fn index(&self, i: usize, g: usize) -> usize {
+        i * (self.groups.len() + 1) + g
+    }
+    /// The state after taking `step` at cell `i` with group `g` next, if the known cells allow it.
+    fn next(&self, i: usize, g: usize, step: Step) -> Option<(usize, usize)> {
+        match step {
+            Step::Empty => (self.cells[i] != Some(true)).then_some((i + 1, g)),
+            Step::Group => {
+                let end = i + self.groups.get(g)?;
+                let fits = end <= self.cells.len()
+                    && self.empty_before[end] == self.empty_before[i]
+                    && self.cells.get(end) != Some(&Some(true));
+                // the group takes its separator along, unless it ends the line
+                fits.then_some(((end + 1).min(self.cells.len()), g + 1))
+            }
+        }
+    }
+    /// Steps out of `(i, g)` that still lead to a full arrangement, with how many each.
+    fn steps(&self, i: usize, g: usize) -> impl Iterator<Item = (Step, (usize, usize), u128)> + '_ {
+        [Step::Empty, Step::Group]
+            .into_iter()
+            .filter_map(move |step| {
+                let (next_i, next_g) = self.next(i, g, step)?;
+                let ways = self.ways[self.index(next_i, next_g)];
+                (ways > 0).then_some((step, (next_i, next_g), ways))
+            })
+    }
+    pub fn count(&self) -> u128 {
+        self.ways[self.index(0, 0)]
+    }
+    /// Fills in the cells along a path of steps.
+    fn fill(&self, path: &[(Step, usize, usize)]) -> Vec<bool> {
+        let mut line = vec![false; self.cells.len()];
+        for &(step, i, g) in path {
+            if step == Step::Group {
+                line[i..i + self.groups[g]].fill(true);
+            }
+        }
+        line
+    }
+    /// Up to `limit` arrangements, the ones with the groups furthest left first.
+    pub fn arrangements(&self, limit: usize) -> Vec<Vec<bool>> {
+        let mut found = vec![];
+        let mut path = vec![];
+        self.collect(0, 0, limit, &mut path, &mut found);
+        found
+    }
+    fn collect(
+        &self,
+        i: usize,
+        g: usize,
+        limit: usize,
+        path: &mut Vec<(Step, usize, usize)>,
+        found: &mut Vec<Vec<bool>>,
+    ) {
+        if found.len() >= limit {
+            return;
+        }
+        if i == self.cells.len() {
+            if self.ways[self.index(i, g)] > 0 {
+                found.push(self.fill(path));
+            }
+            return;
+        }
+        let mut steps: Vec<_> = self.steps(i, g).collect();
+        steps.sort_by_key(|&(step, _, _)| step != Step::Group);
+        for (step, (next_i, next_g), _) in steps {
+            path.push((step, i, g));
+            self.collect(next_i, next_g, limit, path, found);
+            path.pop();
+        }
+    }
+    /// One arrangement, every one of them equally likely.
+    pub fn sample(&self, rng: &mut Rng) -> Option<Vec<bool>> {
+        if self.count() == 0 {
+            return None;
+        }
+        let (mut i, mut g) = (0, 0);
+        let mut path = vec![];
+        // pick the arrangement number `r`, then follow the steps that contain it
+        let mut r = rng.below(self.count());
+        while i < self.cells.len() {
+            for (step, (next_i, next_g), ways) in self.steps(i, g) {
+                if r < ways {
+                    path.push((step, i, g));
+                    (i, g) = (next_i, next_g);
+                    break;
+                }
+                r -= ways;
+            }
+        }
+        Some(self.fill(&path))
+    }
+    /// For every cell, whether some arrangement fills it and whether some leaves it empty.
+    pub fn possibilities(&self) -> Vec<(bool, bool)> {
+        let mut can_fill = vec![0i64; self.cells.len() + 1];
+        let mut can_empty = vec![false; self.cells.len()];
+        let mut reachable = vec![false; self.ways.len()];
+        reachable[self.index(0, 0)] = self.count() > 0;
+        for i in 0..self.cells.len() {
+            for g in 0..=self.groups.len() {
+                if !reachable[self.index(i, g)] {
+                    continue;
+                }
+                for (step, (next_i, next_g), _) in self.steps(i, g) {
+                    reachable[self.index(next_i, next_g)] = true;
+                    match step {
+                        Step::Empty => can_empty[i] = true,
+                        Step::Group => {
+                            // a difference array, the group covers `i..end`
+                            let end = i + self.groups[g];
+                            can_fill[i] += 1;
+                            can_fill[end] -= 1;
+                            if end < self.cells.len() {
+                                can_empty[end] = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let mut covering = 0;
+        can_empty
+            .into_iter()
+            .enumerate()
+            .map(|(i, empty)| {
+                covering += can_fill[i];
+                (covering > 0, empty)
+            })
+            .collect()
+    }
+    /// The value every arrangement agrees on for each cell, `None` where they differ
+    /// or there is no arrangement at all.
+    pub fn forced(&self) -> Vec<Option<bool>> {
+        self.possibilities()
+            .into_iter()
+            .map(|possible| match possible {
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A 2D nonogram: the groups of filled cells in every row, top to bottom,
+/// and in every column, left to right.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    rows: Vec<Vec<usize>>,
+    columns: Vec<Vec<usize>>,
+}
+
+pub enum Solution {
+    Impossible,
+    Unique(Grid<bool>),
+    /// two of the solutions
+    Multiple(Grid<bool>, Grid<bool>),
+}
+
+impl Puzzle {
+    /// Line solving until nothing changes, then guessing the first unknown cell and
+    /// backtracking on contradictions. Stops as soon as a second solution shows up.
+    pub fn solve(&self) -> Solution {
+        let mut solutions = vec![];
+        let grid = Grid::new(self.columns.len(), self.rows.len());
+        self.search(grid, &mut solutions);
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (None, _) => Solution::Impossible,
+            (Some(solution), None) => Solution::Unique(solution),
+            (Some(first), Some(second)) => Solution::Multiple(first, second),
+        }
+    }
+    fn search(&self, mut grid: Grid<Option<bool>>, solutions: &mut Vec<Grid<bool>>) {
+        if solutions.len() >= 2 || !self.propagate(&mut grid) {
+            return;
+        }
+        match grid
+            .iter()
+            .find(|(_, cell)| cell.is_none())
+            .map(|(point, _)| point)
+        {
+            None => solutions
+                .push(Grid::from_iter(grid.iter_rows().map(|(_, row)| {
+                    row.map(|(_, cell)| cell.unwrap()).collect::<Vec<_>>()
+                }))),
+            Some(unknown) => {
+                for guess in [true, false] {
+                    let mut guessed = grid.clone();
+                    guessed[&unknown] = Some(guess);
+                    log::trace!("guessing {} at {}", guess, unknown);
+                    self.search(guessed, solutions);
+                }
+            }
+        }
+    }
+    /// Fills in every cell forced by its row or column. False on a contradiction.
+    fn propagate(&self, grid: &mut Grid<Option<bool>>) -> bool {
+        let lines: Vec<(Vec<Point<usize>>, &[usize])> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(y, groups)| {
+                let points = (0..grid.len_x()).map(|x| Point { x, y }).collect();
+                (points, groups.as_slice())
+            })
+            .chain(self.columns.iter().enumerate().map(|(x, groups)| {
+                let points = (0..grid.len_y()).map(|y| Point { x, y }).collect();
+                (points, groups.as_slice())
+            }))
+            .collect();
+        let mut dirty = vec![true; lines.len()];
+        while let Some(line) = dirty.iter().position(|&dirty| dirty) {
+            dirty[line] = false;
+            let (points, groups) = &lines[line];
+            let cells: Vec<_> = points.iter().map(|point| grid[point]).collect();
+            let solver = LineSolver::new(&cells, groups);
+            if solver.count() == 0 {
+                return false;
+            }
+            for ((point, cell), forced) in points.iter().zip(cells.iter()).zip(solver.forced()) {
+                if cell.is_none() && forced.is_some() {
+                    grid[point] = forced;
+                    // the crossing line: columns come after the rows
+                    let crossing = if line < self.rows.len() {
+                        self.rows.len() + point.x
+                    } else {
+                        point.y
+                    };
+                    dirty[crossing] = true;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Rows, an empty line, then columns. Each line holds the comma separated groups,
+/// or `0` for a line with no filled cells.
+impl FromStr for Puzzle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_block = |block: &str| -> anyhow::Result<Vec<Vec<usize>>> {
+            block
+                .lines()
+                .map(|line| {
+                    line.split(',')
+                        .map(|group| Ok(group.trim().parse()?))
+                        .filter(|group| !matches!(group, Ok(0)))
+                        .collect()
+                })
+                .collect()
+        };
+        let s = s.replace("\r\n", "\n");
+        let (rows, columns) = s.trim().split_once("\n\n").ok_or_else(|| {
+            anyhow::anyhow!("expected rows and columns separated by an empty line")
+        })?;
+        let puzzle = Self {
+            rows: parse_block(rows)?,
+            columns: parse_block(columns)?,
+        };
+        let filled = |lines: &[Vec<usize>]| lines.iter().flatten().sum::<usize>();
+        if filled(&puzzle.rows) != filled(&puzzle.columns) {
+            return Err(anyhow::anyhow!(
+                "rows fill {} cells but columns fill {}",
+                filled(&puzzle.rows),
+                filled(&puzzle.columns)
+            ));
+        }
+        Ok(puzzle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(s: &str) -> Vec<Option<bool>> {
+        s.chars()
+            .map(|c| match c {
+                '#' => Some(true),
+                '.' => Some(false),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn render(line: &[bool]) -> String {
+        line.iter()
+            .map(|&filled| if filled { '#' } else { '.' })
+            .collect()
+    }
+
+    #[test]
+    fn test_count() {
+        let count = |s: &str, groups: &[usize]| LineSolver::new(&cells(s), groups).count();
+        assert_eq!(1, count("???.###", &[1, 1, 3]));
+        assert_eq!(4, count(".??..??...?##.", &[1, 1, 3]));
+        assert_eq!(10, count("?###????????", &[3, 2, 1]));
+        assert_eq!(1, count("...", &[]));
+        assert_eq!(0, count(".#.", &[]));
+        assert_eq!(0, count("??", &[3]));
+    }
+
+    #[test]
+    fn test_arrangements_and_sample() {
+        let line = cells("????");
+        let solver = LineSolver::new(&line, &[1, 1]);
+        let all: Vec<_> = solver.arrangements(10).iter().map(|l| render(l)).collect();
+        assert_eq!(vec!["#.#.", "#..#", ".#.#"], all);
+        assert_eq!(2, solver.arrangements(2).len());
+
+        let mut rng = Rng::seeded(1);
+        for _ in 0..20 {
+            let sample = render(&solver.sample(&mut rng).unwrap());
+            assert!(all.contains(&sample));
+        }
+    }
+
+    #[test]
+    fn test_forced() {
+        let line = cells("?????");
+        assert_eq!(
+            vec![None, Some(true), Some(true), Some(true), None],
+            LineSolver::new(&line, &[4]).forced()
+        );
+        let line = cells("?#??");
+        assert_eq!(
+            vec![Some(false), Some(true), Some(false), Some(true)],
+            LineSolver::new(&line, &[1, 1]).forced()
+        );
+        assert_eq!(vec![None; 2], LineSolver::new(&cells("??"), &[3]).forced());
+    }
+
+    fn render_grid(grid: &Grid<bool>) -> String {
+        grid.display_with_rule(|(_, &filled)| if filled { '#' } else { '.' })
+            .to_string()
+    }
+
+    #[test]
+    fn test_puzzle() {
+        let puzzle: Puzzle = "1,1\n3\n1\n\n2\n2\n2".parse().unwrap();
+        match puzzle.solve() {
+            Solution::Unique(grid) => assert_eq!("\n#.#\n###\n.#.", render_grid(&grid)),
+            _ => panic!("expected a unique solution"),
+        }
+
+        let puzzle: Puzzle = "1\n1\n\n1\n1".parse().unwrap();
+        assert!(matches!(puzzle.solve(), Solution::Multiple(_, _)));
+
+        let puzzle: Puzzle = "2\n0\n\n1\n0\n1".parse().unwrap();
+        assert!(matches!(puzzle.solve(), Solution::Impossible));
+        assert!("1\n\n0".parse::<Puzzle>().is_err());
+    }
+}
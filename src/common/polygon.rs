@@ -0,0 +1,54 @@
+use crate::common::helpers::greatest_common_denominator;
+use crate::common::models::Point;
+
+/// Twice the area of a simple polygon, from the shoelace formula. Always an integer
+/// for integer vertices. The last vertex connects back to the first one.
+pub fn double_area(vertices: &[Point<i64>]) -> i64 {
+    let signed: i64 = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum();
+    signed.abs()
+}
+
+/// Lattice points on the edges of a polygon with integer vertices.
+pub fn boundary_points(vertices: &[Point<i64>]) -> i64 {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| greatest_common_denominator(a.x.abs_diff(b.x), a.y.abs_diff(b.y)) as i64)
+        .sum()
+}
+
+/// Lattice points strictly inside a polygon with integer vertices, from Pick's theorem:
+/// `area = interior + boundary / 2 - 1`.
+pub fn interior_points(vertices: &[Point<i64>]) -> i64 {
+    (double_area(vertices) - boundary_points(vertices)) / 2 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(vertices: &[(i64, i64)]) -> Vec<Point<i64>> {
+        vertices.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    #[test]
+    fn test_rectangle() {
+        let rectangle = points(&[(0, 0), (4, 0), (4, 3), (0, 3)]);
+        assert_eq!(24, double_area(&rectangle));
+        assert_eq!(14, boundary_points(&rectangle));
+        assert_eq!(6, interior_points(&rectangle));
+    }
+
+    #[test]
+    fn test_orientation_and_diagonals() {
+        // clockwise, with a diagonal edge going through (2, 2)
+        let triangle = points(&[(0, 0), (0, 4), (4, 0)]);
+        assert_eq!(16, double_area(&triangle));
+        assert_eq!(12, boundary_points(&triangle));
+        assert_eq!(3, interior_points(&triangle));
+    }
+}
@@ -0,0 +1,46 @@
+/// A small seeded generator (SplitMix64), so sampled results can be reproduced.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(seed)
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// Uniform in `0..bound`, without the bias of a plain modulo.
+    pub fn below(&mut self, bound: u128) -> u128 {
+        assert!(bound > 0, "empty range");
+        // values from `zone` up would make the lowest results more likely
+        let zone = u128::MAX - u128::MAX % bound;
+        loop {
+            let value = ((self.next_u64() as u128) << 64) | self.next_u64() as u128;
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded() {
+        let values: Vec<_> = (0..5).map(|_| Rng::seeded(7).below(1000)).collect();
+        assert!(values.iter().all(|&value| value == values[0]));
+
+        let mut rng = Rng::seeded(42);
+        let mut seen = [false; 6];
+        for _ in 0..200 {
+            seen[rng.below(6) as usize] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::common::exact::{ExactError, Rational};
+use anyhow::{anyhow, bail};
+use std::fmt::{Display, Formatter};
+
+/// Row 0 is the sequence itself, every next row holds the differences of the previous one.
+/// Stops at the first row that is all zeros, or when no values are left.
+pub fn difference_table(values: &[i128]) -> Vec<Vec<i128>> {
+    let mut table = vec![values.to_vec()];
+    loop {
+        let last = table.last().unwrap();
+        if last.is_empty() || last.iter().all(|&v| v == 0) {
+            break;
+        }
+        let next = last
+            .windows(2)
+            .map(|values| values[1] - values[0])
+            .collect();
+        table.push(next);
+    }
+    table
+}
+
+/// Degree of the polynomial that generates `values`, if the finite differences
+/// reach a (non-empty) row of zeros before the values run out.
+pub fn polynomial_degree(values: &[i128]) -> Option<usize> {
+    let table = difference_table(values);
+    let zeros = table.last()?;
+    if zeros.is_empty() {
+        return None;
+    }
+    Some(table.len().saturating_sub(2))
+}
+
+/// Extends the polynomial sequence by `steps`: positive steps go past the last value,
+/// negative steps go before the first one.
+pub fn extrapolate(values: &[i128], steps: i128) -> anyhow::Result<i128> {
+    let table = difference_table(values);
+    if table.last().is_none_or(|zeros| zeros.is_empty()) {
+        bail!(
+            "{} values are not enough to tell the polynomial apart",
+            values.len()
+        );
+    }
+    let overflow = || anyhow!("extrapolating {} steps overflows i128", steps);
+    // Newton's formula: `k` steps past the end is the sum of C(k + j - 1, j) times the last
+    // difference of each row j, `k` steps before the start alternates over the first ones
+    let k = steps.checked_abs().ok_or_else(overflow)?;
+    let mut binomial = 1i128;
+    let mut sum = 0i128;
+    for (j, row) in table.iter().enumerate() {
+        if j > 0 {
+            binomial = binomial
+                .checked_mul(k + j as i128 - 1)
+                .ok_or_else(overflow)?
+                / j as i128;
+        }
+        let difference = match steps {
+            0 => return Ok(*values.last().unwrap()),
+            1.. => row[row.len() - 1],
+            _ if j % 2 == 1 => -row[0],
+            _ => row[0],
+        };
+        sum = binomial
+            .checked_mul(difference)
+            .and_then(|term| sum.checked_add(term))
+            .ok_or_else(overflow)?;
+    }
+    Ok(sum)
+}
+
+/// A polynomial with exact rational coefficients, lowest power first.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<Rational>,
+}
+
+impl Polynomial {
+    /// The Lagrange polynomial: the lowest degree polynomial through all the `(x, y)` points.
+    pub fn fit(points: &[(i128, i128)]) -> Result<Self, ExactError> {
+        let mut coefficients = vec![Rational::zero(); points.len()];
+        for (i, &(x_i, y_i)) in points.iter().enumerate() {
+            // basis polynomial: product of (x - x_j) / (x_i - x_j) for every j != i
+            let mut basis = vec![Rational::integer(y_i)];
+            for (j, &(x_j, _)) in points.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let scale = Rational::integer(x_i).checked_sub(&Rational::integer(x_j))?;
+                if scale.is_zero() {
+                    return Err(ExactError::Degenerate);
+                }
+                let root = Rational::integer(x_j).checked_div(&scale)?;
+                let mut next = vec![Rational::zero(); basis.len() + 1];
+                for (power, value) in basis.iter().enumerate() {
+                    let shifted = value.checked_div(&scale)?;
+                    next[power + 1] = next[power + 1].checked_add(&shifted)?;
+                    next[power] = next[power].checked_sub(&value.checked_mul(&root)?)?;
+                }
+                basis = next;
+            }
+            for (coefficient, value) in coefficients.iter_mut().zip(basis.iter()) {
+                *coefficient = coefficient.checked_add(value)?;
+            }
+        }
+        while coefficients.last().is_some_and(|c| c.is_zero()) {
+            coefficients.pop();
+        }
+        Ok(Self { coefficients })
+    }
+    pub fn evaluate(&self, x: i128) -> Result<Rational, ExactError> {
+        let x = Rational::integer(x);
+        self.coefficients
+            .iter()
+            .rev()
+            .try_fold(Rational::zero(), |sum, coefficient| {
+                sum.checked_mul(&x)?.checked_add(coefficient)
+            })
+    }
+    pub fn evaluate_integer(&self, x: i128) -> Result<i128, ExactError> {
+        self.evaluate(x)?.to_integer()
+    }
+}
+
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut terms = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .peekable();
+        if terms.peek().is_none() {
+            return write!(f, "0");
+        }
+        for (i, (power, &coefficient)) in terms.enumerate() {
+            let coefficient = match (i, coefficient.is_negative()) {
+                (0, _) => coefficient,
+                (_, true) => {
+                    write!(f, " - ")?;
+                    -coefficient
+                }
+                (_, false) => {
+                    write!(f, " + ")?;
+                    coefficient
+                }
+            };
+            let coefficient = if coefficient.is_integer() {
+                coefficient.to_string()
+            } else {
+                format!("({})", coefficient)
+            };
+            match power {
+                0 => write!(f, "{}", coefficient)?,
+                1 => write!(f, "{}x", coefficient)?,
+                _ => write!(f, "{}x^{}", coefficient, power)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polynomial_degree() {
+        assert_eq!(Some(1), polynomial_degree(&[0, 3, 6, 9, 12, 15]));
+        assert_eq!(Some(2), polynomial_degree(&[1, 3, 6, 10, 15, 21]));
+        assert_eq!(Some(0), polynomial_degree(&[5, 5]));
+        assert_eq!(Some(0), polynomial_degree(&[0]));
+        assert_eq!(None, polynomial_degree(&[5]));
+        assert_eq!(None, polynomial_degree(&[1, 2, 4, 8]));
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        assert_eq!(18, extrapolate(&[0, 3, 6, 9, 12, 15], 1).unwrap());
+        assert_eq!(28, extrapolate(&[1, 3, 6, 10, 15, 21], 1).unwrap());
+        assert_eq!(68, extrapolate(&[10, 13, 16, 21, 30, 45], 1).unwrap());
+        assert_eq!(5, extrapolate(&[10, 13, 16, 21, 30, 45], -1).unwrap());
+        assert_eq!(-3, extrapolate(&[0, 3, 6, 9, 12, 15], -1).unwrap());
+        assert_eq!(55, extrapolate(&[1, 3, 6, 10, 15, 21], 4).unwrap());
+        assert_eq!(15, extrapolate(&[0, 3, 6, 9, 12, 15], 0).unwrap());
+        assert_eq!(-6, extrapolate(&[0, 3, 6, 9, 12, 15], -2).unwrap());
+        assert_eq!(6, extrapolate(&[1, 3, 6, 10, 15, 21], -5).unwrap());
+        assert!(extrapolate(&[1, 2, 4, 8], 1).is_err());
+        assert!(extrapolate(&[0, 1 << 100, 1 << 101], 1 << 30).is_err());
+    }
+
+    #[test]
+    fn test_fit() {
+        let polynomial = Polynomial::fit(&[(0, 1), (1, 3), (2, 6)]).unwrap();
+        assert_eq!("(1/2)x^2 + (3/2)x + 1", polynomial.to_string());
+        let negative = Polynomial::fit(&[(0, -34), (1, 130), (2, 456)]).unwrap();
+        assert_eq!("81x^2 + 83x - 34", negative.to_string());
+        assert_eq!(Ok(21), polynomial.evaluate_integer(5));
+        assert_eq!(Ok(Rational::zero()), polynomial.evaluate(-2));
+
+        let line = Polynomial::fit(&[(-3, 1), (5, 5), (1, 3)]).unwrap();
+        assert_eq!(Ok(Rational::new(5, 2).unwrap()), line.evaluate(0));
+        assert!(line.evaluate_integer(0).is_err());
+        assert_eq!(
+            Err(ExactError::Degenerate),
+            Polynomial::fit(&[(1, 1), (1, 2)])
+        );
+    }
+}
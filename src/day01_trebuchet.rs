@@ -1,5 +1,6 @@
+use crate::common::aho_corasick::AhoCorasick;
 use crate::common::day_setup::Day;
-use std::collections::HashMap;
+use std::cmp::Reverse;
 
 pub fn day() -> Day {
     Day::new(run).with_test_inputs(&[
@@ -19,69 +20,101 @@ zoneight234
 }
 
 fn run(input: &str) {
-    let mut sum = 0;
-    for word in input.lines() {
-        let first = if let Some(first) = word.chars().find(|c| c.is_numeric()) {
-            first
-        } else {
-            continue;
-        };
-        let last = word.chars().filter(|c| c.is_numeric()).next_back().unwrap();
-        let value = format!("{}{}", first, last).parse::<u32>().unwrap();
-        sum += value;
-    }
+    let matcher = DigitMatcher::new(Vocabulary::digits());
+    println!("part 1 sum: {}", matcher.calibration_sum(input));
 
-    println!("part 1 sum: {}", sum);
+    let matcher = DigitMatcher::new(Vocabulary::digits().with_words(Vocabulary::ENGLISH));
+    println!("part 2 sum: {}", matcher.calibration_sum(input));
+}
 
-    let map = HashMap::from([
-        ("one", "1"),
-        ("two", "2"),
-        ("three", "3"),
-        ("four", "4"),
-        ("five", "5"),
-        ("six", "6"),
-        ("seven", "7"),
-        ("eight", "8"),
-        ("nine", "9"),
-    ]);
+/// The tokens that count as digits, and the number each one stands for.
+#[derive(Debug, Clone, Default)]
+struct Vocabulary(Vec<(String, u32)>);
 
-    let mut sum = 0;
-    for word in input.lines() {
-        let mut first = None;
-        let mut last = None;
+impl Vocabulary {
+    pub const ENGLISH: &'static [(&'static str, u32)] = &[
+        ("one", 1),
+        ("two", 2),
+        ("three", 3),
+        ("four", 4),
+        ("five", 5),
+        ("six", 6),
+        ("seven", 7),
+        ("eight", 8),
+        ("nine", 9),
+    ];
 
-        for i in 0..word.len() {
-            let word = &word[i..];
-            let value = if let Some(value) = word
-                .chars()
-                .map(|c| if c.is_numeric() { Some(c) } else { None })
-                .next()
-                .flatten()
-            {
-                value.to_string()
-            } else if let Some(value) = map
-                .iter()
-                .filter(|(text, _)| word.starts_with(*text))
-                .map(|(_, value)| value)
-                .next()
-            {
-                value.to_string()
-            } else {
-                continue;
-            };
+    pub fn digits() -> Self {
+        Self((0..=9).map(|digit| (digit.to_string(), digit)).collect())
+    }
+    /// Words can overlap each other ("nineight") and stand for multi-digit numbers ("twelve").
+    pub fn with_words<S: ToString>(mut self, words: &[(S, u32)]) -> Self {
+        self.0
+            .extend(words.iter().map(|(word, value)| (word.to_string(), *value)));
+        self
+    }
+}
 
-            if first.is_none() {
-                first = Some(value.clone());
-            }
-            last = Some(value);
-        }
+struct DigitMatcher(AhoCorasick<u32>);
 
-        let value = format!("{}{}", first.unwrap(), last.unwrap())
-            .parse::<u32>()
-            .unwrap();
-        log::debug!("value: {}", value);
-        sum += value;
+struct FirstAndLast {
+    first: usize,
+    first_value: u32,
+    last: usize,
+    last_value: u32,
+}
+
+impl FirstAndLast {
+    /// The first and last values written next to each other.
+    pub fn calibration_value(&self) -> u64 {
+        format!("{}{}", self.first_value, self.last_value)
+            .parse()
+            .unwrap()
     }
+}
 
-    println!("part 2 sum: {}", sum);
+impl DigitMatcher {
+    pub fn new(vocabulary: Vocabulary) -> Self {
+        Self(AhoCorasick::new(vocabulary.0))
+    }
+    /// Positions are byte offsets where the tokens start. Tokens starting at the same
+    /// position prefer the longest one, so "seventeen" wins over "seven".
+    pub fn first_and_last(&self, line: &str) -> Option<FirstAndLast> {
+        let first = self
+            .0
+            .find_overlapping(line)
+            .min_by_key(|m| (m.start, Reverse(m.end)))?;
+        let last = self
+            .0
+            .find_overlapping(line)
+            .max_by_key(|m| (m.start, m.end))
+            .unwrap();
+        Some(FirstAndLast {
+            first: first.start,
+            first_value: *first.value,
+            last: last.start,
+            last_value: *last.value,
+        })
+    }
+    /// Lines without any digit do not count towards the sum, they get reported instead.
+    pub fn calibration_sum(&self, input: &str) -> u64 {
+        let mut sum = 0;
+        for (i, line) in input.lines().enumerate() {
+            match self.first_and_last(line) {
+                Some(found) => {
+                    log::debug!(
+                        "line {}: first {} at {}, last {} at {}",
+                        i + 1,
+                        found.first_value,
+                        found.first,
+                        found.last_value,
+                        found.last
+                    );
+                    sum += found.calibration_value();
+                }
+                None => log::warn!("line {}: no digits found in '{}'", i + 1, line),
+            }
+        }
+        sum
+    }
 }
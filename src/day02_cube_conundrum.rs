@@ -1,36 +1,136 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
 use anyhow::Context as AnyhowContext;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+    Day::custom(run).with_test_inputs(&["Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
 Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
 Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
 Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"])
 }
-pub fn run(input: &str) {
-    let games: Vec<Game> = input.lines().map(|line| line.parse().unwrap()).collect();
-    let set = Set {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
-    let sum: usize = games
-        .iter()
-        .filter(|game| game.is_possible(&set))
-        .map(|game| game.id)
-        .sum();
+/// Takes `bag=<count> <color>, ...` to check the games against another bag, and `top=<k>` for
+/// the bag with the lowest power that makes at least `k` games possible.
+pub fn run(context: &AppContext) {
+    let games: Games = context.get_input().parse().unwrap();
+    let mut bag: Set = "12 red, 13 green, 14 blue".parse().unwrap();
+    let mut top = None;
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "bag" => bag = value.parse().unwrap(),
+            "top" => top = Some(value.parse().expect("invalid number of games")),
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+
+    let sum: usize = games.feasible_for(&bag).map(|game| game.id).sum();
     println!("part 1 sum: {}", sum);
 
-    let sum: usize = games
-        .iter()
-        .map(|game| {
-            let min_set = game.get_min_set();
-            min_set.get_power()
-        })
-        .sum();
+    let sum: usize = games.games.iter().map(|game| games.power(game)).sum();
     println!("part 2 sum of powers: {}", sum);
+    log::debug!("power distribution: {:?}", games.power_distribution());
+
+    if let Some(k) = top {
+        let (ids, bag) = games.minimal_bag_for_any(k).expect("not that many games");
+        println!("games {:?} are all possible with: {}", ids, bag);
+    }
+}
+
+struct Games {
+    games: Vec<Game>,
+    /// every color seen in the input
+    colors: BTreeSet<String>,
+}
+
+impl Games {
+    pub fn feasible_for<'a>(&'a self, bag: &'a Set) -> impl Iterator<Item = &'a Game> + 'a {
+        self.games.iter().filter(|game| game.is_possible(bag))
+    }
+    /// The power of the smallest bag that makes `game` possible.
+    /// Colors the game never shows count as 0.
+    pub fn power(&self, game: &Game) -> usize {
+        game.get_min_set().get_power(self.colors.iter())
+    }
+    /// The smallest bag, by power and then by number of cubes, that makes at least `k` games
+    /// possible, with the ids of every game it makes possible. `None` if there are fewer
+    /// than `k` games.
+    pub fn minimal_bag_for_any(&self, k: usize) -> Option<(Vec<usize>, Set)> {
+        if k > self.games.len() {
+            return None;
+        }
+        let needs: Vec<_> = self.games.iter().map(|game| game.get_min_set()).collect();
+        let colors: Vec<_> = self.colors.iter().collect();
+        let candidates: Vec<_> = (0..self.games.len()).collect();
+        let mut best = None;
+        self.search_bags(&needs, &colors, k, &candidates, Set::default(), &mut best);
+        let bag = best?;
+        Some((self.feasible_for(&bag).map(|game| game.id).collect(), bag))
+    }
+    /// Tries every count of `colors[0]` that some candidate game needs, keeping the games that
+    /// still fit. The last color only needs the `k`-th smallest count of the games left.
+    fn search_bags(
+        &self,
+        needs: &[Set],
+        colors: &[&String],
+        k: usize,
+        candidates: &[usize],
+        bag: Set,
+        best: &mut Option<Set>,
+    ) {
+        let key = |bag: &Set| {
+            (
+                bag.get_power(self.colors.iter()),
+                bag.0.values().sum::<usize>(),
+            )
+        };
+        match colors {
+            [] => {
+                if best.as_ref().is_none_or(|best| key(&bag) < key(best)) {
+                    *best = Some(bag);
+                }
+            }
+            [color] => {
+                let mut counts: Vec<_> = candidates
+                    .iter()
+                    .map(|&game| needs[game].count(color))
+                    .collect();
+                counts.sort_unstable();
+                let mut bag = bag;
+                let count = k.checked_sub(1).map_or(0, |i| counts[i]);
+                bag.0.insert(color.to_string(), count);
+                self.search_bags(needs, &[], k, candidates, bag, best);
+            }
+            [color, rest @ ..] => {
+                let counts: BTreeSet<_> = std::iter::once(0)
+                    .chain(candidates.iter().map(|&game| needs[game].count(color)))
+                    .collect();
+                for count in counts {
+                    let fitting: Vec<_> = candidates
+                        .iter()
+                        .copied()
+                        .filter(|&game| needs[game].count(color) <= count)
+                        .collect();
+                    if fitting.len() < k {
+                        continue;
+                    }
+                    let mut bag = bag.clone();
+                    bag.0.insert(color.to_string(), count);
+                    self.search_bags(needs, rest, k, &fitting, bag, best);
+                }
+            }
+        }
+    }
+    /// How many games there are for each power.
+    pub fn power_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut distribution = BTreeMap::new();
+        for game in self.games.iter() {
+            *distribution.entry(self.power(game)).or_default() += 1;
+        }
+        distribution
+    }
 }
 
 struct Game {
@@ -38,24 +138,27 @@ struct Game {
     sets: Vec<Set>,
 }
 
-#[derive(Default)]
-struct Set {
-    red: usize,
-    blue: usize,
-    green: usize,
-}
+#[derive(Default, Debug, Clone, Eq, PartialEq)]
+struct Set(BTreeMap<String, usize>);
 
 impl Set {
+    pub fn count(&self, color: &str) -> usize {
+        self.0.get(color).copied().unwrap_or_default()
+    }
     pub fn contains(&self, other: &Self) -> bool {
-        self.red >= other.red && self.blue >= other.blue && self.green >= other.green
+        other
+            .0
+            .iter()
+            .all(|(color, &count)| self.count(color) >= count)
     }
-    pub fn get_power(&self) -> usize {
-        self.red * self.blue * self.green
+    pub fn get_power<'a>(&self, colors: impl Iterator<Item = &'a String>) -> usize {
+        colors.map(|color| self.count(color)).product()
     }
     pub fn expand_to(&mut self, other: &Self) {
-        self.red = self.red.max(other.red);
-        self.blue = self.blue.max(other.blue);
-        self.green = self.green.max(other.green);
+        for (color, &count) in other.0.iter() {
+            let current = self.0.entry(color.clone()).or_default();
+            *current = (*current).max(count);
+        }
     }
 }
 
@@ -71,6 +174,23 @@ impl Game {
     }
 }
 
+impl FromStr for Games {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let games: Vec<Game> = s
+            .lines()
+            .map(|line| line.parse())
+            .collect::<Result<_, _>>()?;
+        let colors = games
+            .iter()
+            .flat_map(|game| game.sets.iter())
+            .flat_map(|set| set.0.keys().cloned())
+            .collect();
+        Ok(Self { games, colors })
+    }
+}
+
 impl FromStr for Game {
     type Err = anyhow::Error;
 
@@ -88,23 +208,39 @@ impl FromStr for Game {
             .next()
             .context("no game body")?
             .split(";")
-            .map(|set_str| {
-                let mut set = Set::default();
-                for part in set_str.trim().split(",") {
-                    let mut parts = part.split_whitespace();
-                    let count: usize = parts.next().context("no numeric part")?.parse()?;
-                    let color = match parts.next().context("no color part")? {
-                        "red" => &mut set.red,
-                        "blue" => &mut set.blue,
-                        "green" => &mut set.green,
-                        other => return Err(anyhow::anyhow!("invalid color '{}'", other)),
-                    };
-                    *color += count;
-                }
-                Ok(set)
-            })
+            .map(|set_str| set_str.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self { id, sets })
     }
 }
+
+impl FromStr for Set {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut set = Set::default();
+        for part in s.trim().split(",") {
+            let mut parts = part.split_whitespace();
+            let count: usize = parts.next().context("no numeric part")?.parse()?;
+            let color = parts.next().context("no color part")?;
+            if let Some(other) = parts.next() {
+                return Err(anyhow::anyhow!("unexpected '{}' after color", other));
+            }
+            *set.0.entry(color.to_string()).or_default() += count;
+        }
+        Ok(set)
+    }
+}
+
+impl Display for Set {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (color, count)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {}", count, color)?;
+        }
+        Ok(())
+    }
+}
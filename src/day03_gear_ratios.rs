@@ -1,7 +1,9 @@
 use crate::common::day_setup::Day;
 use crate::common::models::grid::GridLike;
 use crate::common::models::{DirectionFlag, Grid, Point};
-use std::collections::{HashMap, HashSet};
+use colored::Colorize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
 use std::str::FromStr;
 
 pub fn day() -> Day {
@@ -18,104 +20,173 @@ pub fn day() -> Day {
 }
 pub fn run(input: &str) {
     let schematic: Schematic = input.parse().unwrap();
-    let sum = schematic.get_sum_part_numbers();
+    log::debug!("{}", schematic.render());
+
+    let sum: u64 = schematic.part_numbers().map(|number| number.value).sum();
     println!("part 1 sum: {}", sum);
 
-    let sum = schematic.get_sum_gear_ratios();
+    let sum: u64 = schematic
+        .symbols_with_neighbors(Some('*'), 2)
+        .map(|symbol| schematic.neighbor_product(symbol))
+        .sum();
     println!("part 2 sum gear ratios: {}", sum);
+
+    for kind in schematic.symbol_kinds() {
+        let symbols: Vec<_> = schematic.symbols_of(Some(kind)).collect();
+        log::debug!(
+            "symbol '{}': {} found, neighbor sum {}",
+            kind,
+            symbols.len(),
+            symbols
+                .iter()
+                .map(|&symbol| schematic.neighbor_sum(symbol))
+                .sum::<u64>()
+        );
+    }
+    log::debug!(
+        "numbers next to no symbol: {:?}",
+        schematic
+            .lonely_numbers()
+            .map(|number| number.value)
+            .collect::<Vec<_>>()
+    );
 }
 
+/// Numbers and symbols, indexed by which of them touch each other.
 struct Schematic {
-    numbers: HashMap<Point<usize>, Number>,
-    asterisks: Vec<Point<usize>>,
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    /// for each symbol, the indices of the numbers next to it
+    symbol_neighbors: Vec<Vec<usize>>,
+    /// for each number, the indices of the symbols next to it
+    number_neighbors: Vec<Vec<usize>>,
+    /// index of the number covering each digit cell
+    number_cells: HashMap<Point<usize>, usize>,
     grid: Grid<char>,
 }
 
 impl Schematic {
-    fn number_is_part(&self, number: &Number) -> bool {
-        log::trace!("starting number {:?}", number);
-        let mut point = number.position;
-        let mut neighbors = self.grid.neighbors(
-            &point,
-            DirectionFlag::LEFT
-                | DirectionFlag::UP_LEFT
-                | DirectionFlag::DOWN_LEFT
-                | DirectionFlag::UP
-                | DirectionFlag::DOWN,
-        );
-        for _ in 1..number.len {
-            point.x += 1;
-            neighbors.extend(
-                self.grid
-                    .neighbors(&point, DirectionFlag::UP | DirectionFlag::DOWN),
-            );
+    fn new(grid: Grid<char>, numbers: Vec<Number>) -> Self {
+        let mut number_cells = HashMap::new();
+        for (i, number) in numbers.iter().enumerate() {
+            for x in number.position.x..number.position.x + number.len {
+                number_cells.insert(
+                    Point {
+                        x,
+                        y: number.position.y,
+                    },
+                    i,
+                );
+            }
+        }
+
+        let mut symbols = vec![];
+        let mut symbol_neighbors = vec![];
+        let mut number_neighbors = vec![vec![]; numbers.len()];
+        for (point, &kind) in grid.iter() {
+            if kind == '.' || kind.is_numeric() {
+                continue;
+            }
+            let neighbors: BTreeSet<usize> = grid
+                .neighbors(&point, DirectionFlag::ALL_DIRECTIONS)
+                .iter()
+                .filter_map(|neighbor| number_cells.get(neighbor).copied())
+                .collect();
+            for &number in neighbors.iter() {
+                number_neighbors[number].push(symbols.len());
+            }
+            symbols.push(Symbol {
+                position: point,
+                kind,
+            });
+            symbol_neighbors.push(neighbors.into_iter().collect());
+        }
+
+        Self {
+            numbers,
+            symbols,
+            symbol_neighbors,
+            number_neighbors,
+            number_cells,
+            grid,
         }
-        neighbors.extend(self.grid.neighbors(
-            &point,
-            DirectionFlag::RIGHT | DirectionFlag::UP_RIGHT | DirectionFlag::DOWN_RIGHT,
-        ));
-
-        neighbors.iter().any(|neighbor| {
-            let is_neighbor = self.is_symbol(neighbor);
-            log::trace!(
-                "position {} is symbol: '{}'={}",
-                neighbor,
-                self.grid[neighbor],
-                is_neighbor
-            );
-            is_neighbor
-        })
     }
-    fn is_symbol(&self, point: &Point<usize>) -> bool {
-        let c = self.grid[point];
-        c != '.' && !c.is_numeric()
+    /// Every distinct symbol character, sorted.
+    pub fn symbol_kinds(&self) -> BTreeSet<char> {
+        self.symbols.iter().map(|symbol| symbol.kind).collect()
     }
-    pub fn get_sum_part_numbers(&self) -> u64 {
-        self.numbers
-            .values()
-            .filter(|number| {
-                if self.number_is_part(number) {
-                    log::debug!("number {} is next to a symbol", number.value);
-                    true
-                } else {
-                    log::debug!("number {} is NOT next to a symbol", number.value);
-                    false
-                }
-            })
-            .map(|number| number.value)
-            .sum()
+    /// Symbol indices of the given kind, or of any kind for `None`.
+    pub fn symbols_of(&self, kind: Option<char>) -> impl Iterator<Item = usize> + '_ {
+        (0..self.symbols.len())
+            .filter(move |&symbol| kind.is_none_or(|kind| self.symbols[symbol].kind == kind))
     }
-    fn get_two_adjacent_numbers(&self, point: &Point<usize>) -> Option<(u64, u64)> {
-        let numbers: HashSet<_> = self
-            .grid
-            .neighbors(point, DirectionFlag::ALL_DIRECTIONS)
-            .into_iter()
-            .filter(|neighbor| self.grid[neighbor].is_numeric())
-            .map(|neighbor| self.get_number_at(neighbor))
-            .collect();
-
-        if numbers.len() == 2 {
-            let mut numbers = numbers.into_iter();
-            Some((numbers.next().unwrap().value, numbers.next().unwrap().value))
-        } else {
-            None
-        }
+    pub fn symbols_with_neighbors(
+        &self,
+        kind: Option<char>,
+        count: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        self.symbols_of(kind)
+            .filter(move |&symbol| self.symbol_neighbors[symbol].len() == count)
     }
-    fn get_number_at(&self, mut point: Point<usize>) -> Number {
-        loop {
-            if let Some(number) = self.numbers.get(&point) {
-                return number.clone();
-            }
-            point.x -= 1;
-        }
+    pub fn neighbors_of_symbol(&self, symbol: usize) -> impl Iterator<Item = &Number> {
+        self.symbol_neighbors[symbol]
+            .iter()
+            .map(|&number| &self.numbers[number])
     }
-    pub fn get_sum_gear_ratios(&self) -> u64 {
-        self.asterisks
+    pub fn neighbors_of_number(&self, number: usize) -> impl Iterator<Item = &Symbol> {
+        self.number_neighbors[number]
             .iter()
-            .filter_map(|asterisk| self.get_two_adjacent_numbers(asterisk))
-            .map(|(left, right)| left * right)
+            .map(|&symbol| &self.symbols[symbol])
+    }
+    pub fn neighbor_product(&self, symbol: usize) -> u64 {
+        self.neighbors_of_symbol(symbol)
+            .map(|number| number.value)
+            .product()
+    }
+    pub fn neighbor_sum(&self, symbol: usize) -> u64 {
+        self.neighbors_of_symbol(symbol)
+            .map(|number| number.value)
             .sum()
     }
+    /// Numbers next to at least one symbol.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.neighbors_of_number(i).next().is_some())
+            .map(|(_, number)| number)
+    }
+    /// Numbers next to no symbol at all.
+    pub fn lonely_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.neighbors_of_number(i).next().is_none())
+            .map(|(_, number)| number)
+    }
+    /// Part numbers in green, lonely numbers in red, and symbols touching numbers in yellow.
+    pub fn render(&self) -> impl Display + '_ {
+        self.grid.display_overriding(|point| {
+            if let Some(&number) = self.number_cells.get(point) {
+                let c = self.grid[point].to_string();
+                return Some(if self.number_neighbors[number].is_empty() {
+                    c.red()
+                } else {
+                    c.green()
+                });
+            }
+            let c = self.grid[point];
+            if c == '.' {
+                return Some(c.to_string().dimmed());
+            }
+            let touches_number = self
+                .grid
+                .neighbors(point, DirectionFlag::ALL_DIRECTIONS)
+                .iter()
+                .any(|neighbor| self.number_cells.contains_key(neighbor));
+            touches_number.then(|| c.to_string().yellow().bold())
+        })
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
@@ -125,14 +196,19 @@ struct Number {
     value: u64,
 }
 
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+struct Symbol {
+    position: Point<usize>,
+    kind: char,
+}
+
 impl FromStr for Schematic {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let grid = Grid::from_iter(s.lines().map(|line| line.chars()));
 
-        let mut asterisks: Vec<Point<usize>> = Default::default();
-        let mut numbers: HashMap<Point<usize>, Number> = HashMap::new();
+        let mut numbers: Vec<Number> = vec![];
         let mut add_number = |number: Vec<char>, x: usize, y: usize| -> Result<(), anyhow::Error> {
             if number.is_empty() {
                 return Ok(());
@@ -140,14 +216,11 @@ impl FromStr for Schematic {
             let len = number.len();
             let value = number.into_iter().collect::<String>().parse()?;
             let position = Point { x, y };
-            numbers.insert(
+            numbers.push(Number {
                 position,
-                Number {
-                    position,
-                    len,
-                    value,
-                },
-            );
+                len,
+                value,
+            });
 
             Ok(())
         };
@@ -156,10 +229,6 @@ impl FromStr for Schematic {
             let mut number = vec![];
             let mut x = 0;
             for c in line.chars() {
-                if c == '*' {
-                    asterisks.push(Point { x, y })
-                }
-
                 if c.is_numeric() {
                     number.push(c);
                 } else if x > 0 {
@@ -172,10 +241,6 @@ impl FromStr for Schematic {
             add_number(number, x, y)?;
         }
 
-        Ok(Self {
-            numbers,
-            asterisks,
-            grid,
-        })
+        Ok(Self::new(grid, numbers))
     }
 }
@@ -1,18 +1,21 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
 use anyhow::Context as AnyhowContext;
 use std::collections::HashSet;
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+    Day::custom(run).with_test_inputs(&["Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
 Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
 Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
 Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
 Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
 Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"])
 }
-pub fn run(input: &str) {
-    let cards: Vec<Card> = input
+/// Takes `window=<value>` and `overflow=<value>` to select the copy rules,
+/// see [Window] and [Overflow] for the accepted values.
+pub fn run(context: &AppContext) {
+    let cards: Vec<Card> = context
+        .get_input()
         .lines()
         .map(|line| line.parse())
         .collect::<Result<Vec<_>, _>>()
@@ -21,18 +24,201 @@ pub fn run(input: &str) {
     let sum: u32 = cards.iter().map(|card| card.get_points()).sum();
     println!("part 1 sum: {}", sum);
 
-    let mut cards: Vec<_> = cards.into_iter().map(|card| (1usize, card)).collect();
-    for i in 0..cards.len() {
-        let (copies, card) = &cards[i];
-        let copies = *copies;
-        let count = card.get_winning_count();
-        for (card_count, _) in cards[i + 1..i + 1 + count].iter_mut() {
-            *card_count += copies;
+    let mut rules = CopyRules::default();
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "window" => rules.window = value.parse().unwrap(),
+            "overflow" => rules.overflow = value.parse().unwrap(),
+            other => panic!("unknown argument '{}'", other),
         }
     }
+    log::debug!("copy rules: {:?}", rules);
+    let cascade = Cascade::new(&cards, rules).unwrap();
+    for (round, cards) in cascade.rounds().iter().enumerate() {
+        log::debug!("round {}: {} new cards", round, cards.iter().sum::<usize>());
+    }
+    for card in 0..cards.len() {
+        log::trace!(
+            "card {}: {} copies, from {:?}",
+            card + 1,
+            cascade.copies(card),
+            cascade.sources(card)
+        );
+    }
+
+    println!("part 2 total scratchcards: {}", cascade.total());
+}
+
+/// How many of the following cards a card copies.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+enum Window {
+    /// `matches`: as many cards as winning numbers
+    #[default]
+    WinningCount,
+    /// `points`: as many cards as the card is worth in points
+    Points,
+    /// `<n>`: always `n` cards, as long as the card has any winning number
+    Fixed(usize),
+}
+
+/// What happens to copies that would go past the last card.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+enum Overflow {
+    /// `strict`: it is an error
+    #[default]
+    Strict,
+    /// `cap`: they are lost
+    Cap,
+    /// `wrap`: they continue from the first card
+    Wrap,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+struct CopyRules {
+    window: Window,
+    overflow: Overflow,
+}
+
+impl CopyRules {
+    /// Indices of the cards that `card` wins a copy of.
+    fn targets(&self, cards: &[Card], card: usize) -> anyhow::Result<Vec<usize>> {
+        let window = match self.window {
+            _ if cards[card].get_winning_count() == 0 => 0,
+            Window::WinningCount => cards[card].get_winning_count(),
+            Window::Points => cards[card].get_points() as usize,
+            Window::Fixed(window) => window,
+        };
+        let targets = card + 1..=card + window;
+        match self.overflow {
+            Overflow::Strict => {
+                if window > 0 && *targets.end() >= cards.len() {
+                    return Err(anyhow::anyhow!(
+                        "card {} copies past the last card {}",
+                        card + 1,
+                        cards.len()
+                    ));
+                }
+                Ok(targets.collect())
+            }
+            Overflow::Cap => Ok(targets.take_while(|&i| i < cards.len()).collect()),
+            Overflow::Wrap => Ok(targets.map(|i| i % cards.len()).collect()),
+        }
+    }
+}
+
+/// Every card won, round by round: round 0 is the original table,
+/// every next round holds the copies won by the cards of the previous one.
+struct Cascade {
+    rounds: Vec<Vec<usize>>,
+    copies: Vec<usize>,
+    total: usize,
+    /// for each card, the cards that won copies of it
+    sources: Vec<Vec<usize>>,
+}
+
+impl Cascade {
+    pub fn new(cards: &[Card], rules: CopyRules) -> anyhow::Result<Self> {
+        let targets: Vec<_> = (0..cards.len())
+            .map(|card| rules.targets(cards, card))
+            .collect::<Result<_, _>>()?;
+        let mut sources = vec![vec![]; cards.len()];
+        for (card, targets) in targets.iter().enumerate() {
+            for &target in targets {
+                sources[target].push(card);
+            }
+        }
+
+        let mut rounds = vec![vec![1; cards.len()]];
+        loop {
+            let last = rounds.last().unwrap();
+            if last.iter().all(|&count| count == 0) {
+                rounds.pop();
+                break;
+            }
+            // without cycles every chain of copies ends within one round per card
+            if rounds.len() > cards.len() {
+                return Err(Self::forever());
+            }
+            let mut next = vec![0usize; cards.len()];
+            for (card, &count) in last.iter().enumerate() {
+                for &target in targets[card].iter() {
+                    // cycles can double the counts every round, long before the check above
+                    next[target] = next[target].checked_add(count).ok_or_else(Self::forever)?;
+                }
+            }
+            rounds.push(next);
+        }
+
+        let copies: Vec<usize> = (0..cards.len())
+            .map(|card| {
+                rounds
+                    .iter()
+                    .try_fold(0usize, |sum, round| sum.checked_add(round[card]))
+                    .ok_or_else(Self::forever)
+            })
+            .collect::<Result<_, _>>()?;
+        let total = copies
+            .iter()
+            .try_fold(0usize, |sum, &count| sum.checked_add(count))
+            .ok_or_else(Self::forever)?;
+        Ok(Self {
+            rounds,
+            copies,
+            total,
+            sources,
+        })
+    }
+    fn forever() -> anyhow::Error {
+        anyhow::anyhow!("the cards keep copying each other forever")
+    }
+    /// For each round, how many copies of each card were won.
+    pub fn rounds(&self) -> &[Vec<usize>] {
+        &self.rounds
+    }
+    /// Copies of `card` at the end, the original included.
+    pub fn copies(&self, card: usize) -> usize {
+        self.copies[card]
+    }
+    /// The cards that won copies of `card`, and how many each of them won.
+    pub fn sources(&self, card: usize) -> Vec<(usize, usize)> {
+        self.sources[card]
+            .iter()
+            .map(|&source| (source, self.copies[source]))
+            .collect()
+    }
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+impl FromStr for Window {
+    type Err = anyhow::Error;
 
-    let count: usize = cards.iter().map(|(count, _)| *count).sum();
-    println!("part 2 total scratchcards: {}", count);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "matches" => Ok(Self::WinningCount),
+            "points" => Ok(Self::Points),
+            other => Ok(Self::Fixed(
+                other
+                    .parse()
+                    .with_context(|| format!("invalid window '{}'", other))?,
+            )),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "cap" => Ok(Self::Cap),
+            "wrap" => Ok(Self::Wrap),
+            other => Err(anyhow::anyhow!("invalid overflow '{}'", other)),
+        }
+    }
 }
 
 struct Card {
@@ -1,6 +1,6 @@
 use crate::common::day_setup::Day;
 use anyhow::Context as AnyhowContext;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 
 pub fn day() -> Day {
     Day::new(run).with_test_inputs(&["seeds: 79 14 55 13
@@ -50,10 +50,20 @@ pub fn run(input: &str) {
         .collect::<Result<Vec<_>, _>>()
         .unwrap();
 
-    let maps = Mappings::try_from(blocks).unwrap();
+    let almanac = Almanac::try_from(blocks).unwrap();
+    let path = almanac
+        .path("seed", "location")
+        .context("no path from seed to location")
+        .unwrap();
+    log::debug!("path: {:?}", path);
+    let maps = almanac.compose("seed", "location").unwrap();
 
     let min = seeds.iter().map(|&seed| maps.map(seed)).min().unwrap();
     println!("lowest location: {}", min);
+    match almanac.compose("location", "seed") {
+        Ok(locations) => log::debug!("location {} comes from seed {}", min, locations.map(min)),
+        Err(e) => log::debug!("no reverse lookup: {}", e),
+    }
 
     let seeds: Vec<_> = seeds
         .chunks_exact(2)
@@ -69,6 +79,7 @@ pub fn run(input: &str) {
         .min()
         .unwrap();
     println!("lowest location part 2: {}", min);
+    log::debug!("location {} comes from seeds {:?}", min, maps.preimage(min));
 }
 
 struct SeedRange {
@@ -85,41 +96,41 @@ impl SeedRanges {
     }
     pub fn insert(&mut self, range: SeedRange) {
         // check for largest start <= range.start
-        if let Some((&start, &len)) = self.0.range(..=range.start).next_back() {
-            if range.start < start + len {
-                let new_len = range.len + (range.start - start);
-                if new_len > len {
-                    let new_range = SeedRange {
-                        start,
-                        len: new_len,
-                    };
-                    self.0.remove(&start);
-                    self.insert(new_range);
-                }
-                return;
-            }
-        }
-
-        // check for next (min) start > range.start
-        if let Some((&start, &len)) = self.0.range(range.start..).next() {
-            if start < range.start + range.len {
-                let new_len = range.len.max(len + (start - range.start));
+        if let Some((&start, &len)) = self.0.range(..=range.start).next_back()
+            && range.start < start + len
+        {
+            let new_len = range.len + (range.start - start);
+            if new_len > len {
                 let new_range = SeedRange {
-                    start: range.start,
+                    start,
                     len: new_len,
                 };
                 self.0.remove(&start);
                 self.insert(new_range);
-                return;
             }
+            return;
+        }
+
+        // check for next (min) start > range.start
+        if let Some((&start, &len)) = self.0.range(range.start..).next()
+            && start < range.start + range.len
+        {
+            let new_len = range.len.max(len + (start - range.start));
+            let new_range = SeedRange {
+                start: range.start,
+                len: new_len,
+            };
+            self.0.remove(&start);
+            self.insert(new_range);
+            return;
         }
 
         self.0.insert(range.start, range.len);
     }
 }
 
+/// Piecewise map: values inside a range are shifted by its delta, values outside stay the same.
 struct Map {
-    #[allow(dead_code)]
     source_name: String,
     destination_name: String,
     map: BTreeMap<i64, MapValue>,
@@ -137,12 +148,15 @@ impl MapValue {
     }
 }
 
+/// Bounds for composing and inverting maps, far from anything in the input but without overflow.
+const DOMAIN: (i64, i64) = (i64::MIN / 4, i64::MAX / 4);
+
 impl Map {
     pub fn map(&self, value: i64) -> i64 {
-        if let Some((&start, &MapValue { len, delta })) = self.map.range(..=value).next_back() {
-            if value < start + len {
-                return value + delta;
-            }
+        if let Some((&start, &MapValue { len, delta })) = self.map.range(..=value).next_back()
+            && value < start + len
+        {
+            return value + delta;
         }
 
         value
@@ -156,24 +170,23 @@ impl Map {
                 delta: map_delta,
             },
         )) = self.map.range(..=start).next_back()
+            && start < map_start + map_len
         {
-            if start < map_start + map_len {
-                let map_len_diff = map_len - (start - map_start);
-                if len <= map_len_diff {
-                    return vec![SeedRange {
-                        start: start + map_delta,
-                        len,
-                    }];
-                }
-                let mut result = vec![SeedRange {
+            let map_len_diff = map_len - (start - map_start);
+            if len <= map_len_diff {
+                return vec![SeedRange {
                     start: start + map_delta,
-                    len: map_len_diff,
+                    len,
                 }];
-                let extra_len = len - map_len_diff;
-                let extra_start = start + map_len_diff;
-                result.extend(self.map_from(extra_start, extra_len));
-                return result;
             }
+            let mut result = vec![SeedRange {
+                start: start + map_delta,
+                len: map_len_diff,
+            }];
+            let extra_len = len - map_len_diff;
+            let extra_start = start + map_len_diff;
+            result.extend(self.map_from(extra_start, extra_len));
+            return result;
         }
 
         // check for next (min) map_start > start
@@ -184,24 +197,23 @@ impl Map {
                 delta: map_delta,
             },
         )) = self.map.range(start..).next()
+            && map_start < start + len
         {
-            if map_start < start + len {
-                let new_len = (len - (map_start - start)).min(map_len);
-                let mut results = vec![SeedRange {
-                    start: map_start + map_delta,
-                    len: new_len,
-                }];
+            let new_len = (len - (map_start - start)).min(map_len);
+            let mut results = vec![SeedRange {
+                start: map_start + map_delta,
+                len: new_len,
+            }];
 
-                let extra_len = map_start - start;
-                results.extend(self.map_from(start, extra_len));
-
-                let len_diff = len - (map_start - start) - map_len;
-                if len_diff > 0 {
-                    results.extend(self.map_from(map_start + map_len, len_diff));
-                }
+            let extra_len = map_start - start;
+            results.extend(self.map_from(start, extra_len));
 
-                return results;
+            let len_diff = len - (map_start - start) - map_len;
+            if len_diff > 0 {
+                results.extend(self.map_from(map_start + map_len, len_diff));
             }
+
+            return results;
         }
 
         vec![SeedRange { start, len }]
@@ -218,59 +230,216 @@ impl Map {
     }
 }
 
-struct Mappings([Map; 7]);
-
-impl Mappings {
-    pub fn map(&self, seed: i64) -> i64 {
-        log::debug!("seed {}", seed);
-        let mut value = seed;
-        for map in self.0.iter() {
-            value = map.map(value);
-            log::debug!("{} {}", map.destination_name, value);
+impl Map {
+    /// Splits `[start, end)` into pieces that each get a single delta, gaps included.
+    fn pieces(&self, start: i64, end: i64) -> Vec<(i64, i64, i64)> {
+        let mut breakpoints = vec![start, end];
+        for (&map_start, value) in self.map.iter() {
+            breakpoints.extend(
+                [map_start, map_start + value.len]
+                    .into_iter()
+                    .filter(|&point| start < point && point < end),
+            );
         }
-
-        value
+        breakpoints.sort_unstable();
+        breakpoints.dedup();
+        breakpoints
+            .windows(2)
+            .map(|window| (window[0], window[1], self.map(window[0]) - window[0]))
+            .collect()
     }
-    pub fn map_range_to_lowest(&self, range: SeedRange) -> i64 {
-        let mut ranges = SeedRanges::new(range);
-        for map in self.0.iter() {
-            ranges = map.map_range(ranges);
+    fn from_pieces(
+        source_name: String,
+        destination_name: String,
+        pieces: impl Iterator<Item = (i64, i64, i64)>,
+    ) -> Self {
+        let mut map: BTreeMap<i64, MapValue> = BTreeMap::new();
+        for (start, end, delta) in pieces.filter(|&(_, _, delta)| delta != 0) {
+            // merge with the previous piece if it continues it
+            if let Some((&previous, value)) = map.iter_mut().next_back()
+                && previous + value.len == start
+                && value.delta == delta
+            {
+                value.len += end - start;
+                continue;
+            }
+            map.insert(
+                start,
+                MapValue {
+                    len: end - start,
+                    delta,
+                },
+            );
+        }
+        Self {
+            source_name,
+            destination_name,
+            map,
+        }
+    }
+    /// A single map doing `self` and then `next`.
+    pub fn then(&self, next: &Map) -> Self {
+        let pieces = self
+            .pieces(DOMAIN.0, DOMAIN.1)
+            .into_iter()
+            .flat_map(|(start, end, delta)| {
+                next.pieces(start + delta, end + delta).into_iter().map(
+                    move |(next_start, next_end, next_delta)| {
+                        (next_start - delta, next_end - delta, delta + next_delta)
+                    },
+                )
+            });
+        Self::from_pieces(
+            self.source_name.clone(),
+            next.destination_name.clone(),
+            pieces,
+        )
+    }
+    /// Every value that maps to `value`.
+    pub fn preimage(&self, value: i64) -> Vec<i64> {
+        self.pieces(DOMAIN.0, DOMAIN.1)
+            .into_iter()
+            .map(|(start, end, delta)| (start..end, value - delta))
+            .filter(|(range, source)| range.contains(source))
+            .map(|(_, source)| source)
+            .collect()
+    }
+    /// The reverse map, if no two values map to the same one.
+    pub fn invert(&self) -> Option<Self> {
+        let mut pieces: Vec<_> = self
+            .pieces(DOMAIN.0, DOMAIN.1)
+            .into_iter()
+            .map(|(start, end, delta)| (start + delta, end + delta, -delta))
+            .collect();
+        pieces.sort_unstable();
+        if pieces.windows(2).any(|window| window[0].1 > window[1].0) {
+            return None;
         }
+        Some(Self::from_pieces(
+            self.destination_name.clone(),
+            self.source_name.clone(),
+            pieces.into_iter(),
+        ))
+    }
+    pub fn map_range_to_lowest(&self, range: SeedRange) -> i64 {
+        let ranges = self.map_range(SeedRanges::new(range));
         *ranges.0.first_key_value().unwrap().0
     }
+}
+
+/// All the maps, keyed by their source and destination categories.
+struct Almanac(BTreeMap<(String, String), Map>);
+
+impl Almanac {
+    /// The categories from `from` to `to`, following the maps forwards.
+    pub fn path<'a>(&'a self, from: &'a str, to: &'a str) -> Option<Vec<&'a str>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![to];
+                while let Some(&before) = previous.get(path.last().unwrap()) {
+                    path.push(before);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (_, next) in self.0.keys().filter(|(source, _)| source == current) {
+                if next != from && !previous.contains_key(next.as_str()) {
+                    previous.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+    /// A single map from `from` to `to`. Goes backwards through inverted maps
+    /// if the categories only connect the other way round.
+    pub fn compose(&self, from: &str, to: &str) -> anyhow::Result<Map> {
+        if let Some(path) = self.path(from, to) {
+            return Ok(self.compose_along(&path));
+        }
+        if let Some(path) = self.path(to, from) {
+            return self
+                .compose_along(&path)
+                .invert()
+                .with_context(|| format!("the map from {} to {} cannot be inverted", to, from));
+        }
+        Err(anyhow::anyhow!("no path between {} and {}", from, to))
+    }
+    /// The maps along `path`, one after the other.
+    fn compose_along(&self, path: &[&str]) -> Map {
+        let identity = Map {
+            source_name: path[0].to_string(),
+            destination_name: path[0].to_string(),
+            map: BTreeMap::new(),
+        };
+        path.windows(2).fold(identity, |acc, step| {
+            acc.then(&self.0[&(step[0].to_string(), step[1].to_string())])
+        })
+    }
     pub fn try_from<'a>(blocks: impl Iterator<Item = &'a str>) -> anyhow::Result<Self> {
-        Ok(Self(
-            blocks
-                .map(|block| {
-                    let mut lines = block.lines();
-                    let mut names = lines
-                        .next()
-                        .unwrap()
-                        .split_whitespace()
-                        .next()
-                        .context("empty name")?
-                        .split("-");
-                    let source_name = names.next().context("no source name")?.to_string();
-                    let destination_name = names.nth(1).context("no destination name")?.to_string();
-                    Ok(Map {
-                        source_name,
-                        destination_name,
-                        map: lines
-                            .map(|line| {
-                                let mut numbers =
-                                    line.split_whitespace().map(|word| word.parse::<i64>());
-                                let dest = numbers.next().context("no dest number")??;
-                                let source = numbers.next().context("no source number")??;
-                                let len = numbers.next().context("no len number")??;
-                                Ok((source, MapValue::new(source, dest, len)))
-                            })
-                            .collect::<anyhow::Result<BTreeMap<_, _>>>()?,
+        let mut maps = BTreeMap::new();
+        for block in blocks {
+            let mut lines = block.lines();
+            let mut names = lines
+                .next()
+                .unwrap()
+                .split_whitespace()
+                .next()
+                .context("empty name")?
+                .split("-");
+            let source_name = names.next().context("no source name")?.to_string();
+            let destination_name = names.nth(1).context("no destination name")?.to_string();
+            let key = (source_name.clone(), destination_name.clone());
+            if maps.contains_key(&key) {
+                return Err(anyhow::anyhow!(
+                    "duplicate {}-to-{} map",
+                    source_name,
+                    destination_name
+                ));
+            }
+            let map = Map {
+                source_name,
+                destination_name,
+                map: lines
+                    .map(|line| {
+                        let mut numbers = line.split_whitespace().map(|word| word.parse::<i64>());
+                        let dest = numbers.next().context("no dest number")??;
+                        let source = numbers.next().context("no source number")??;
+                        let len = numbers.next().context("no len number")??;
+                        Ok((source, MapValue::new(source, dest, len)))
                     })
-                })
-                .collect::<anyhow::Result<Vec<_>>>()?
-                .try_into()
-                .ok()
-                .context("expected 7 maps")?,
-        ))
+                    .collect::<anyhow::Result<BTreeMap<_, _>>>()?,
+            };
+            maps.insert(key, map);
+        }
+        Ok(Self(maps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> anyhow::Result<Almanac> {
+        Almanac::try_from(input.split("\n\n"))
+    }
+
+    #[test]
+    fn test_compose_without_path() {
+        let almanac = parse("seed-to-soil map:\n50 98 2\n98 50 2").unwrap();
+        assert_eq!(98, almanac.compose("soil", "seed").unwrap().map(50));
+        assert!(almanac.compose("seed", "location").is_err());
+        assert!(almanac.compose("location", "seed").is_err());
+        assert!(almanac.compose("nothing", "else").is_err());
+
+        let almanac = parse("seed-to-soil map:\n50 98 2\n\nwater-to-light map:\n1 2 3").unwrap();
+        assert!(almanac.compose("seed", "light").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_maps() {
+        assert!(parse("seed-to-soil map:\n50 98 2\n\nseed-to-soil map:\n1 2 3").is_err());
     }
 }
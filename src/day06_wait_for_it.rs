@@ -1,4 +1,6 @@
 use crate::common::day_setup::Day;
+use anyhow::Context;
+use std::ops::RangeInclusive;
 
 pub fn day() -> Day {
     Day::new(run).with_test_inputs(&["Time:      7  15   30
@@ -7,7 +9,7 @@ Distance:  9  40  200"])
 pub fn run(input: &str) {
     let races = parse(input);
 
-    let product: u64 = races.iter().map(ways_to_beat_record).product();
+    let product: u128 = races.iter().map(ways_to_beat_record).product();
     println!("product part 1: {}", product);
 
     let race = parse2(input);
@@ -15,33 +17,112 @@ pub fn run(input: &str) {
     println!("ways to beat big race: {}", ways);
 }
 
-fn ways_to_beat_record(race: &Race) -> u64 {
+fn ways_to_beat_record(race: &Race) -> u128 {
     log::debug!("race: {:?}", race);
+    let outcomes = race.outcomes().unwrap();
+    log::debug!("outcomes: {:?}", outcomes);
+    outcomes.ways_to_beat()
+}
 
-    // to tie: Tx - x^2 - d = 0
-    let bounds_exclusive = solve_quadratic(1.0, -(race.time as f64), race.best_distance as f64);
-    log::debug!("solution to quadratic {:?}", bounds_exclusive);
-    let bounds_exclusive = [
-        bounds_exclusive[0].floor() as u64,
-        bounds_exclusive[1].ceil() as u64,
-    ];
-    log::debug!(
-        "can match record with ({}) {:?}",
-        bounds_exclusive[1] - bounds_exclusive[0] - 1,
-        bounds_exclusive
-    );
-    bounds_exclusive[1] - bounds_exclusive[0] - 1
+#[derive(Debug)]
+struct Race {
+    time: u128,
+    best_distance: u128,
 }
 
-fn solve_quadratic(a: f64, b: f64, c: f64) -> [f64; 2] {
-    let rhs = (b.powi(2) - 4.0 * a * c).sqrt();
-    [(-b - rhs) / (2.0 * a), (-b + rhs) / (2.0 * a)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct RaceOutcomes {
+    /// hold times that exactly match the record
+    ties: Vec<u128>,
+    /// hold times that beat the record
+    beats: Option<RangeInclusive<u128>>,
+    /// hold time with the longest distance (the other best one is `time - optimal_hold`)
+    optimal_hold: u128,
 }
 
-#[derive(Debug)]
-struct Race {
-    time: u64,
-    best_distance: u64,
+impl RaceOutcomes {
+    pub fn ways_to_beat(&self) -> u128 {
+        self.beats
+            .as_ref()
+            .map_or(0, |beats| beats.end() - beats.start() + 1)
+    }
+}
+
+impl Race {
+    /// `None` if the distance does not fit in u128.
+    pub fn distance(&self, hold: u128) -> Option<u128> {
+        hold.checked_mul(self.time.checked_sub(hold)?)
+    }
+    /// Errors once the longest distance, about `time^2 / 4`, does not fit in u128.
+    pub fn outcomes(&self) -> anyhow::Result<RaceOutcomes> {
+        let time = self.time;
+        let optimal_hold = time / 2;
+        let mut outcomes = RaceOutcomes {
+            ties: vec![],
+            beats: None,
+            optimal_hold,
+        };
+
+        // every other hold goes less far, so all their distances fit too
+        let longest = self
+            .distance(optimal_hold)
+            .context("race is too long to solve exactly")?;
+        if longest < self.best_distance {
+            return Ok(outcomes);
+        }
+        let reaches_record = |hold| self.distance(hold).is_some_and(|d| d >= self.best_distance);
+
+        // holding k less than optimal loses k^2 (k(k+1) for odd times) of the longest distance,
+        // so the first hold time that reaches the record is about sqrt(longest - d) earlier.
+        // The integer square root only gets close, correct it by stepping towards the boundary
+        let earlier = (longest - self.best_distance).isqrt();
+        let mut first = optimal_hold - earlier.min(optimal_hold);
+        while first > 0 && reaches_record(first - 1) {
+            first -= 1;
+        }
+        while !reaches_record(first) {
+            first += 1;
+        }
+
+        let first_beat = if self.distance(first) == Some(self.best_distance) {
+            outcomes.ties.push(first);
+            if time - first != first {
+                outcomes.ties.push(time - first);
+            }
+            first + 1
+        } else {
+            first
+        };
+        if first_beat <= time / 2 {
+            outcomes.beats = Some(first_beat..=time - first_beat);
+        }
+        Ok(outcomes)
+    }
+    /// Tries every hold time, only meant to check [Race::outcomes] on small races.
+    #[cfg(test)]
+    pub fn outcomes_brute_force(&self) -> RaceOutcomes {
+        let distances = (0..=self.time).map(|hold| (hold, self.distance(hold).unwrap()));
+        let beats: Vec<_> = distances
+            .clone()
+            .filter(|&(_, distance)| distance > self.best_distance)
+            .map(|(hold, _)| hold)
+            .collect();
+        RaceOutcomes {
+            ties: distances
+                .clone()
+                .filter(|&(_, distance)| distance == self.best_distance)
+                .map(|(hold, _)| hold)
+                .collect(),
+            beats: beats
+                .first()
+                .zip(beats.last())
+                .map(|(&first, &last)| first..=last),
+            optimal_hold: distances
+                .max_by_key(|&(hold, distance)| (distance, std::cmp::Reverse(hold)))
+                .unwrap()
+                .0,
+        }
+    }
 }
 
 fn parse(input: &str) -> Vec<Race> {
@@ -60,7 +141,7 @@ fn parse(input: &str) -> Vec<Race> {
 
 fn parse2(input: &str) -> Race {
     let mut lines = input.lines();
-    let mut combine_numbers = || -> u64 {
+    let mut combine_numbers = || -> u128 {
         let numbers = lines.next().unwrap().split(":").nth(1).unwrap();
         let numbers = numbers.replace(|c: char| c.is_whitespace(), "");
         numbers.parse().unwrap()
@@ -70,3 +151,62 @@ fn parse2(input: &str) -> Race {
         best_distance: combine_numbers(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::random::Rng;
+
+    fn check(time: u128, best_distance: u128) {
+        let race = Race {
+            time,
+            best_distance,
+        };
+        assert_eq!(
+            race.outcomes_brute_force(),
+            race.outcomes().unwrap(),
+            "{:?}",
+            race
+        );
+    }
+
+    #[test]
+    fn test_outcomes_match_brute_force() {
+        // every record up to past the best distance, which covers every exact square
+        for time in 0..=40 {
+            for best_distance in 0..=time * time / 4 + 2 {
+                check(time, best_distance);
+            }
+        }
+
+        // longer races, with records right around a reachable distance
+        let mut rng = Rng::seeded(6);
+        for _ in 0..500 {
+            let time = rng.below(2000) + 1;
+            let hold = rng.below(time + 1);
+            let distance = hold * (time - hold);
+            for best_distance in distance.saturating_sub(1)..=distance + 1 {
+                check(time, best_distance);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_races() {
+        // the longest distance just fits in u128
+        let optimal_hold = u64::MAX as u128;
+        let race = Race {
+            time: optimal_hold * 2,
+            best_distance: optimal_hold * optimal_hold - 1,
+        };
+        let outcomes = race.outcomes().unwrap();
+        assert_eq!(vec![optimal_hold - 1, optimal_hold + 1], outcomes.ties);
+        assert_eq!(Some(optimal_hold..=optimal_hold), outcomes.beats);
+
+        let race = Race {
+            time: 1 << 66,
+            best_distance: 1,
+        };
+        assert!(race.outcomes().is_err());
+    }
+}
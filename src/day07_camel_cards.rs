@@ -1,253 +1,417 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
 use anyhow::Context as AnyhowContext;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["32T3K 765
+    Day::custom(run).with_test_inputs(&["32T3K 765
 T55J5 684
 KK677 28
 KTJJT 220
 QQQJA 483"])
 }
 
-pub fn run(input: &str) {
-    let mut hands: Vec<Hand> = input
+/// Takes `rules=<camel|jokers|poker>` to also rank the hands under other [Rules].
+pub fn run(context: &AppContext) {
+    let hands: Vec<Hand> = context
+        .get_input()
         .lines()
         .map(|line| line.parse())
         .collect::<Result<Vec<_>, _>>()
         .unwrap();
-    hands.sort_unstable();
 
-    let total_winnings = calculate_winnings(&hands);
+    let total_winnings = calculate_winnings(&Rules::part1(), &hands).unwrap();
     println!("part 1 winnings: {}", total_winnings);
 
-    for hand in hands.iter_mut() {
-        hand.convert_j_to_joker();
-    }
-    hands.sort_unstable();
-
-    let total_winnings = calculate_winnings(&hands);
+    let total_winnings = calculate_winnings(&Rules::part2(), &hands).unwrap();
     println!("part 2 winnings: {}", total_winnings);
+
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "rules" => {
+                let rules = match value {
+                    "camel" => Rules::part1(),
+                    "jokers" => Rules::part2(),
+                    "poker" => Rules::poker(),
+                    other => panic!("unknown rules '{}'", other),
+                };
+                let total_winnings = calculate_winnings(&rules, &hands).unwrap();
+                println!("{} winnings: {}", rules.name, total_winnings);
+            }
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
 }
 
-fn calculate_winnings(sorted_hands: &[Hand]) -> u64 {
-    sorted_hands
+fn calculate_winnings(rules: &Rules, hands: &[Hand]) -> anyhow::Result<u64> {
+    let mut hands = hands
+        .iter()
+        .map(|hand| Ok((rules.evaluate(hand)?, hand)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    hands.sort_unstable_by(|(left, _), (right, _)| left.cmp(right));
+    if let [.., (_, second), (_, best)] = hands.as_slice() {
+        log::debug!("{}: {}", rules.name, rules.explain(best, second)?);
+    }
+
+    Ok(hands
         .iter()
         .enumerate()
-        .map(|(i, hand)| {
+        .map(|(i, (evaluation, hand))| {
             let rank = (i + 1) as u64;
-            log::debug!("hand {} gets rank {}", hand, rank);
+            log::debug!(
+                "hand {} ({}) gets rank {}",
+                hand,
+                evaluation.hand_type,
+                rank
+            );
             rank * hand.bid
         })
-        .sum()
+        .sum())
 }
 
-#[derive(Clone, Eq, PartialEq)]
-struct Hand {
-    cards: [Card; 5],
-    hand_type: HandType,
-    bid: u64,
+/// How hands with the same type are ordered.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TieBreak {
+    /// card by card, in the order they were dealt
+    Lexicographic,
+    /// biggest group first (highest rank among groups of the same size), like poker kickers.
+    /// Wild cards count as the rank they stand for.
+    SortedGroups,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    name: &'static str,
+    /// every valid card, weakest first
+    alphabet: Vec<char>,
+    /// cards that count as whatever makes the hand strongest
+    wild: Vec<char>,
+    hand_size: usize,
+    /// whether consecutive ranks rank between three of a kind and full house
+    straights: bool,
+    tie_break: TieBreak,
 }
 
-impl Hand {
-    fn new(cards: [Card; 5], bid: u64) -> Self {
-        let hand_type = Self::calculate_hand_type(&cards);
+impl Rules {
+    pub fn part1() -> Self {
         Self {
-            cards,
-            hand_type,
-            bid,
+            name: "camel cards",
+            alphabet: "23456789TJQKA".chars().collect(),
+            wild: vec![],
+            hand_size: 5,
+            straights: false,
+            tie_break: TieBreak::Lexicographic,
         }
     }
-    fn calculate_hand_type(cards: &[Card]) -> HandType {
-        let ranks: HashMap<Card, usize> = cards.iter().filter(|&&card| card != Card::Joker).fold(
-            HashMap::default(),
-            |mut acc, &next| {
-                *acc.entry(next).or_default() += 1;
-                acc
-            },
-        );
-        let mut ranks: Vec<_> = ranks.into_values().collect();
-        ranks.sort_unstable();
-
-        let joker_count = cards.iter().filter(|&&card| card == Card::Joker).count();
-        if let Some(last) = ranks.last_mut() {
-            *last += joker_count;
-        } else {
-            ranks = vec![joker_count];
+    /// J becomes a joker, and the weakest card on its own.
+    pub fn part2() -> Self {
+        Self {
+            name: "camel cards with jokers",
+            alphabet: "J23456789TQKA".chars().collect(),
+            wild: vec!['J'],
+            ..Self::part1()
         }
-
-        if ranks.last() == Some(&5) {
-            return HandType::FiveOfAKind;
+    }
+    /// Standard poker ranks, without suits and so without flushes.
+    pub fn poker() -> Self {
+        Self {
+            name: "poker",
+            straights: true,
+            tie_break: TieBreak::SortedGroups,
+            ..Self::part1()
         }
+    }
+    fn rank(&self, card: char) -> anyhow::Result<usize> {
+        self.alphabet
+            .iter()
+            .position(|&c| c == card)
+            .with_context(|| format!("invalid card '{}' for {}", card, self.name))
+    }
+    pub fn evaluate(&self, hand: &Hand) -> anyhow::Result<Evaluation> {
+        if hand.cards.len() != self.hand_size {
+            return Err(anyhow::anyhow!(
+                "hand {} should have {} cards for {}",
+                hand,
+                self.hand_size,
+                self.name
+            ));
+        }
+        let ranks = hand
+            .cards
+            .iter()
+            .map(|&card| self.rank(card))
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-        let last = ranks[ranks.len() - 1];
-        let second_last = ranks[ranks.len() - 2];
-
-        match last {
-            1 => HandType::HighCard,
-            2 => {
-                if second_last == 2 {
-                    HandType::TwoPair
-                } else {
-                    HandType::OnePair
-                }
-            }
-            3 => {
-                if second_last == 2 {
-                    HandType::FullHouse
-                } else {
-                    HandType::ThreeOfAKind
-                }
+        let mut groups: HashMap<usize, usize> = HashMap::new();
+        for (&card, &rank) in hand.cards.iter().zip(ranks.iter()) {
+            if !self.wild.contains(&card) {
+                *groups.entry(rank).or_default() += 1;
             }
-            4 => HandType::FourOfAKind,
-            _ => unreachable!(),
         }
-    }
-    pub fn convert_j_to_joker(&mut self) {
-        for card in self.cards.iter_mut() {
-            if *card == Card::J {
-                *card = Card::Joker;
-            }
+        let wild_count = hand.cards.len() - groups.values().sum::<usize>();
+        let mut counts: Vec<_> = groups.values().copied().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        // wild cards always do best joining the biggest group
+        match counts.first_mut() {
+            Some(biggest) => *biggest += wild_count,
+            None => counts.push(wild_count),
         }
-        self.hand_type = Self::calculate_hand_type(&self.cards);
-    }
-}
-
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let cmp = self.hand_type.cmp(&other.hand_type);
-        if cmp != Ordering::Equal {
-            return cmp;
+        let mut hand_type = HandType {
+            counts,
+            straight: false,
+        };
+        let straight_high = self
+            .straights
+            .then(|| self.straight_high(groups.keys().copied(), wild_count))
+            .flatten();
+        if straight_high.is_some() {
+            // wild cards might still do better in a group than in the straight
+            let straight = HandType {
+                straight: true,
+                ..hand_type.clone()
+            };
+            hand_type = hand_type.max(straight);
         }
-        for (left, right) in self.cards.iter().zip(other.cards.iter()) {
-            let cmp = left.cmp(right);
-            if cmp != Ordering::Equal {
-                return cmp;
+
+        let tie_break = match (self.tie_break, straight_high) {
+            (TieBreak::Lexicographic, _) => ranks,
+            // straights only compare by their highest card
+            (TieBreak::SortedGroups, Some(high)) if hand_type.straight => vec![high],
+            (TieBreak::SortedGroups, _) => {
+                // wild cards take the rank of the group they joined
+                let joined = groups
+                    .iter()
+                    .max_by_key(|&(&rank, &count)| (count, rank))
+                    .map(|(&rank, _)| rank);
+                let mut sorted: Vec<_> = hand
+                    .cards
+                    .iter()
+                    .zip(ranks.iter())
+                    .map(|(card, &rank)| match joined {
+                        Some(joined) if self.wild.contains(card) => joined,
+                        _ => rank,
+                    })
+                    .collect();
+                let counts = sorted.clone();
+                sorted.sort_unstable_by_key(|rank| {
+                    (
+                        Reverse(counts.iter().filter(|&r| r == rank).count()),
+                        Reverse(*rank),
+                    )
+                });
+                sorted
             }
-        }
+        };
 
-        Ordering::Equal
+        Ok(Evaluation {
+            hand_type,
+            tie_break,
+        })
     }
-}
-
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    /// The highest rank a straight can reach, if all the non-wild ranks are different and fit
+    /// in a run of `hand_size` ranks. An ace (the top rank) can also go below the lowest card.
+    fn straight_high(
+        &self,
+        ranks: impl Iterator<Item = usize>,
+        wild_count: usize,
+    ) -> Option<usize> {
+        let ranks: Vec<_> = ranks.collect();
+        if ranks.len() + wild_count != self.hand_size {
+            return None;
+        }
+        let top = self.alphabet.len() - 1;
+        // wild cards extend the run upwards as far as they can
+        let high = |ranks: &[usize]| match ranks.iter().min().zip(ranks.iter().max()) {
+            None => Some(top),
+            Some((min, max)) => {
+                (max - min < self.hand_size).then(|| (min + self.hand_size - 1).min(top))
+            }
+        };
+        let low_ace: Vec<_> = ranks
+            .iter()
+            .map(|&rank| if rank == top { 0 } else { rank + 1 })
+            .collect();
+        // the low ace run shifts every rank one up
+        high(&ranks).max(high(&low_ace).map(|high| high.saturating_sub(1)))
     }
-}
-
-impl FromStr for Hand {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split_whitespace();
-        Ok(Self::new(
-            parts
-                .next()
-                .context("empty input")?
-                .chars()
-                .map(|c| c.into())
-                .collect::<Vec<Card>>()
-                .try_into()
-                .ok()
-                .context("hand should be length 5")?,
-            parts.next().context("no bid found")?.parse()?,
-        ))
+    /// Why `left` ranks above, below or the same as `right`.
+    pub fn explain<'a>(&self, left: &'a Hand, right: &'a Hand) -> anyhow::Result<Explanation<'a>> {
+        let (left_evaluation, right_evaluation) = (self.evaluate(left)?, self.evaluate(right)?);
+        let ordering = left_evaluation.cmp(&right_evaluation);
+        let reason = if left_evaluation.hand_type != right_evaluation.hand_type {
+            Reason::HandType(left_evaluation.hand_type, right_evaluation.hand_type)
+        } else {
+            match left_evaluation
+                .tie_break
+                .iter()
+                .zip(right_evaluation.tie_break.iter())
+                .position(|(l, r)| l != r)
+            {
+                Some(i) => Reason::TieBreak {
+                    hand_type: left_evaluation.hand_type,
+                    position: i,
+                    left: self.alphabet[left_evaluation.tie_break[i]],
+                    right: self.alphabet[right_evaluation.tie_break[i]],
+                    tie_break: self.tie_break,
+                },
+                None => Reason::Equal(left_evaluation.hand_type),
+            }
+        };
+        Ok(Explanation {
+            ordering,
+            left,
+            right,
+            reason,
+        })
     }
 }
 
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+/// The sizes of the groups of equal cards, biggest first.
+#[derive(Debug, Clone)]
+struct HandType {
+    counts: Vec<usize>,
+    straight: bool,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-enum Card {
-    A,
-    K,
-    Q,
-    J,
-    Num(u8),
-    Joker,
+impl HandType {
+    /// Straights rank right above three of a kind and its kickers.
+    fn key(&self) -> (Vec<usize>, bool) {
+        if self.straight {
+            let size: usize = self.counts.iter().sum();
+            let mut counts = vec![1; size.saturating_sub(2).max(1)];
+            counts[0] = 3;
+            (counts, true)
+        } else {
+            (self.counts.clone(), false)
+        }
+    }
 }
 
-impl Card {
-    const fn rank(&self) -> u8 {
-        match self {
-            Card::A => 14,
-            Card::K => 13,
-            Card::Q => 12,
-            Card::J => 11,
-            Card::Num(val) => *val,
-            Card::Joker => 1,
-        }
+impl PartialEq for HandType {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
     }
 }
 
-impl Ord for Card {
+impl Eq for HandType {}
+
+impl Ord for HandType {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.rank().cmp(&other.rank())
+        self.key().cmp(&other.key())
     }
 }
 
-impl PartialOrd for Card {
+impl PartialOrd for HandType {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl From<char> for Card {
-    fn from(value: char) -> Self {
-        match value {
-            'A' => Self::A,
-            'K' => Self::K,
-            'Q' => Self::Q,
-            'J' => Self::J,
-            'T' => Self::Num(10),
-            '2'..='9' => Self::Num(value.to_digit(10).unwrap() as u8),
-            other => panic!("invalid card rank '{}'", other),
+impl Display for HandType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.straight {
+            return write!(f, "straight");
+        }
+        let groups: Vec<_> = self.counts.iter().filter(|&&count| count > 1).collect();
+        match groups.as_slice() {
+            [] => write!(f, "high card"),
+            [2] => write!(f, "one pair"),
+            [2, 2] => write!(f, "two pair"),
+            [3] => write!(f, "three of a kind"),
+            [3, 2] => write!(f, "full house"),
+            [4] => write!(f, "four of a kind"),
+            [5] => write!(f, "five of a kind"),
+            _ => write!(f, "groups of {:?}", groups),
         }
     }
 }
 
-impl Display for Card {
+/// A hand ranked under some [Rules]: compares by hand type, then by tie break ranks.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct Evaluation {
+    hand_type: HandType,
+    tie_break: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum Reason {
+    HandType(HandType, HandType),
+    TieBreak {
+        hand_type: HandType,
+        /// index into the cards in tie break order
+        position: usize,
+        left: char,
+        right: char,
+        tie_break: TieBreak,
+    },
+    Equal(HandType),
+}
+
+#[derive(Debug)]
+struct Explanation<'a> {
+    ordering: Ordering,
+    left: &'a Hand,
+    right: &'a Hand,
+    reason: Reason,
+}
+
+impl Display for Explanation<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Card::A => {
-                write!(f, "A")
-            }
-            Card::K => {
-                write!(f, "K")
-            }
-            Card::Q => {
-                write!(f, "Q")
-            }
-            Card::J => {
-                write!(f, "J")
-            }
-            Card::Joker => {
-                write!(f, "*")
-            }
-            Card::Num(val) => {
-                if *val == 10 {
-                    write!(f, "T")
-                } else {
-                    write!(f, "{}", val)
-                }
+        let verb = match self.ordering {
+            Ordering::Less => "loses to",
+            Ordering::Equal => "ties with",
+            Ordering::Greater => "beats",
+        };
+        write!(f, "{} {} {}: ", self.left, verb, self.right)?;
+        match &self.reason {
+            Reason::HandType(left, right) => write!(f, "{} against {}", left, right),
+            Reason::TieBreak {
+                hand_type,
+                position,
+                left,
+                right,
+                tie_break,
+            } => {
+                let order = match tie_break {
+                    TieBreak::Lexicographic => "card",
+                    TieBreak::SortedGroups => "sorted card",
+                };
+                write!(
+                    f,
+                    "both {}, {} {} is {} against {}",
+                    hand_type,
+                    order,
+                    position + 1,
+                    left,
+                    right
+                )
             }
+            Reason::Equal(hand_type) => write!(f, "both {} with the same cards", hand_type),
         }
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Hand {
+    cards: Vec<char>,
+    bid: u64,
+}
+
+impl FromStr for Hand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        Ok(Self {
+            cards: parts.next().context("empty input")?.chars().collect(),
+            bid: parts.next().context("no bid found")?.parse()?,
+        })
+    }
+}
+
 impl Display for Hand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for card in self.cards.iter() {
@@ -1,14 +1,15 @@
-use crate::common::day_setup::Day;
-use crate::common::helpers::least_common_multiple_for;
+use crate::common::day_setup::{AppContext, Day};
+use crate::common::graph::{reachable, strongly_connected_components};
+use crate::common::helpers::{CongruenceError, chinese_remainder};
 use anyhow::Context as AnyhowContext;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
-use std::fmt::{Debug, Formatter};
+use std::fmt::Write;
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&[
+    Day::custom(run).with_test_inputs(&[
         "LLR
 
 AAA = (BBB, BBB)
@@ -27,8 +28,34 @@ XXX = (XXX, XXX)",
     ])
 }
 
-pub fn run(input: &str) {
-    let network: Network = input.parse().unwrap();
+/// `dot=<file>` also writes the network to `file` in Graphviz DOT format.
+pub fn run(context: &AppContext) {
+    let network: Network = context.get_input().parse().unwrap();
+
+    let analysis = network.analyze();
+    log::debug!(
+        "{} strongly connected components, sizes: {:?}",
+        analysis.components.len(),
+        analysis
+            .components
+            .iter()
+            .map(|c| c.len())
+            .collect::<Vec<_>>()
+    );
+    for (start, ends) in analysis.reachable_ends.iter() {
+        log::debug!("{} can reach {:?}", start, ends);
+    }
+    log::debug!("{} dead nodes: {:?}", analysis.dead.len(), analysis.dead);
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "dot" => {
+                std::fs::write(value, network.to_dot()).unwrap();
+                println!("network written to {}", value);
+            }
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
 
     if network.nodes.contains_key("AAA") {
         let start = "AAA";
@@ -37,13 +64,13 @@ pub fn run(input: &str) {
             "part 1 - steps between {} and {}: {}",
             start,
             end,
-            network.steps_between(start, end)
+            network.steps_between(start, end).unwrap()
         );
     }
 
     println!(
         "part 2 - steps between all A and Z: {}",
-        network.steps_for_all_a_to_z()
+        network.steps_for_all_a_to_z().unwrap()
     );
 }
 
@@ -59,91 +86,223 @@ impl Network {
             nodes: nodes.map(|node| (node.name.clone(), node)).collect(),
         }
     }
-    pub fn steps_between(&self, from: &str, to: &str) -> usize {
-        let mut current = from;
-        const MAX_ITERATIONS: usize = 1_000_000;
-        for i in 0..MAX_ITERATIONS {
-            if current == to {
-                return i;
-            }
-            let direction = self.instructions[i % self.instructions.len()];
-            current = self.nodes[current].get(direction);
-        }
-        panic!("max iterations exhausted");
+    pub fn steps_between(&self, from: &str, to: &str) -> anyhow::Result<u128> {
+        self.steps_until_all_end(&[from], |name| name == to)
     }
-    pub fn steps_for_all_a_to_z(&self) -> usize {
-        let mut current: Vec<_> = self
+    pub fn steps_for_all_a_to_z(&self) -> anyhow::Result<u128> {
+        let starts: Vec<_> = self
             .nodes
             .keys()
             .filter(|name| name.ends_with("A"))
-            .map(|name| NodeHistory::new(name))
+            .map(|name| name.as_str())
             .collect();
-        const MAX_ITERATIONS: usize = 1_000_000;
-        for i in 0..MAX_ITERATIONS {
-            current
-                .iter_mut()
-                .for_each(|node| node.visit(i, self.instructions.len()));
-            if current.iter().all(|node| node.is_done()) {
-                println!("done. Getting least common multiple:\n{:#?}", current);
-                return least_common_multiple_for(
-                    &current
-                        .iter()
-                        .filter_map(|node| node.end_in_z.iter().next().map(|&val| val as u64))
-                        .collect::<Vec<_>>(),
-                ) as usize;
-            }
-            let direction = self.instructions[i % self.instructions.len()];
-            for current in current.iter_mut() {
-                current.next(self.nodes[current.current].get(direction))
-            }
+        if starts.is_empty() {
+            return Err(anyhow::anyhow!("no ghosts start: no node ends in A"));
         }
-        panic!("max iterations exhausted");
+        self.steps_until_all_end(&starts, |name| name.ends_with("Z"))
     }
-}
+    /// Node names sorted, and the left and right edges of each node by index.
+    fn indexed(&self) -> (Vec<&str>, Vec<Vec<usize>>) {
+        let mut names: Vec<&str> = self.nodes.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+        let adjacency = names
+            .iter()
+            .map(|name| {
+                let node = &self.nodes[*name];
+                vec![index[node.left.as_str()], index[node.right.as_str()]]
+            })
+            .collect();
+        (names, adjacency)
+    }
+    /// Looks at the network as a graph, ignoring the instructions.
+    pub fn analyze(&self) -> Analysis<'_> {
+        let (names, adjacency) = self.indexed();
+        let component_ids = strongly_connected_components(&adjacency);
+        let mut components = vec![vec![]; component_ids.iter().max().map_or(0, |&max| max + 1)];
+        for (&name, &component) in names.iter().zip(component_ids.iter()) {
+            components[component].push(name);
+        }
 
-struct NodeHistory<'a> {
-    current: &'a str,
-    visited: HashSet<(&'a str, usize)>,
-    end_in_z: HashSet<usize>,
-    done: bool,
-}
+        let ends: Vec<_> = (0..names.len())
+            .filter(|&i| names[i].ends_with("Z"))
+            .collect();
+        let reachable_ends = (0..names.len())
+            .filter(|&i| names[i].ends_with("A"))
+            .map(|start| {
+                let visited = reachable(&adjacency, [start]);
+                let ends = ends
+                    .iter()
+                    .filter(|&&end| visited[end])
+                    .map(|&end| names[end])
+                    .collect();
+                (names[start], ends)
+            })
+            .collect();
 
-impl<'a> NodeHistory<'a> {
-    pub fn new(name: &'a str) -> Self {
-        Self {
-            current: name,
-            visited: Default::default(),
-            end_in_z: Default::default(),
-            done: false,
+        // a node is dead if no end can be reached from it: walk the edges backwards from the ends
+        let mut reversed = vec![vec![]; names.len()];
+        for (node, edges) in adjacency.iter().enumerate() {
+            for &next in edges {
+                reversed[next].push(node);
+            }
+        }
+        let alive = reachable(&reversed, ends.iter().copied());
+        let dead = (0..names.len())
+            .filter(|&i| !alive[i])
+            .map(|i| names[i])
+            .collect();
+
+        Analysis {
+            components,
+            reachable_ends,
+            dead,
         }
     }
-    pub fn is_done(&self) -> bool {
-        self.done
+    /// Graphviz DOT, with start nodes in green and end nodes in red.
+    pub fn to_dot(&self) -> String {
+        let (names, _) = self.indexed();
+        let mut dot = String::from("digraph network {\n");
+        for name in names {
+            let node = &self.nodes[name];
+            let color = if name.ends_with("A") {
+                ", style=filled, fillcolor=palegreen"
+            } else if name.ends_with("Z") {
+                ", style=filled, fillcolor=lightcoral"
+            } else {
+                ""
+            };
+            writeln!(dot, "  \"{}\" [label=\"{}\"{}];", name, name, color).unwrap();
+            if node.left == node.right {
+                writeln!(dot, "  \"{}\" -> \"{}\" [label=\"LR\"];", name, node.left).unwrap();
+            } else {
+                writeln!(dot, "  \"{}\" -> \"{}\" [label=\"L\"];", name, node.left).unwrap();
+                writeln!(dot, "  \"{}\" -> \"{}\" [label=\"R\"];", name, node.right).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
     }
-    pub fn visit(&mut self, step: usize, instruction_length: usize) {
-        if self.done {
-            return;
+    /// Follows one ghost from `start` until its (node, instruction index) state repeats.
+    fn ghost_path(&self, start: &str, is_end: impl Fn(&str) -> bool) -> GhostPath {
+        let mut seen: HashMap<(&str, usize), usize> = HashMap::new();
+        let mut ends = vec![];
+        let mut current = start;
+        for step in 0.. {
+            let instruction = step % self.instructions.len();
+            if let Some(&cycle_start) = seen.get(&(current, instruction)) {
+                let (prefix_ends, cycle_ends) = ends.iter().partition(|&&end| end < cycle_start);
+                return GhostPath {
+                    prefix: cycle_start,
+                    cycle_len: step - cycle_start,
+                    prefix_ends,
+                    cycle_ends,
+                };
+            }
+            seen.insert((current, instruction), step);
+            if is_end(current) {
+                ends.push(step);
+            }
+            current = self.nodes[current].get(self.instructions[instruction]);
         }
-        if !self
-            .visited
-            .insert((self.current, step % instruction_length))
-        {
-            self.done = true;
-            return;
+        unreachable!()
+    }
+    /// The first step where every ghost is on an end node at the same time.
+    fn steps_until_all_end(
+        &self,
+        starts: &[&str],
+        is_end: impl Fn(&str) -> bool,
+    ) -> anyhow::Result<u128> {
+        let paths: Vec<_> = starts
+            .iter()
+            .map(|start| {
+                let path = self.ghost_path(start, &is_end);
+                log::debug!("{}: {:?}", start, path);
+                path
+            })
+            .collect();
+        let prefix = paths
+            .iter()
+            .map(|path| path.prefix)
+            .max()
+            .unwrap_or_default();
+
+        // arrivals before every ghost is in its cycle can only be checked one by one
+        let early = paths
+            .iter()
+            .max_by_key(|path| path.prefix)
+            .into_iter()
+            .flat_map(|path| path.prefix_ends.iter())
+            .copied()
+            .filter(|&step| paths.iter().all(|path| path.ends_at(step)))
+            .min();
+        if let Some(step) = early {
+            return Ok(step as u128);
         }
-        if self.current.ends_with("Z") {
-            self.end_in_z.insert(step);
-            self.done = true;
+
+        // after that each ghost ends at step = offset (mod cycle_len) for one of its offsets,
+        // so try every combination of offsets, keeping each distinct residue once
+        let mut congruences: HashSet<(i128, i128)> = HashSet::from([(0, 1)]);
+        for path in paths.iter() {
+            let mut combined = HashSet::new();
+            for &congruence in congruences.iter() {
+                for &end in path.cycle_ends.iter() {
+                    match chinese_remainder(congruence, (end as i128, path.cycle_len as i128)) {
+                        Ok(congruence) => {
+                            combined.insert(congruence);
+                        }
+                        Err(CongruenceError::NoSolution) => {}
+                        Err(error @ CongruenceError::Overflow) => {
+                            return Err(error).context("the ghost cycles are too long to combine");
+                        }
+                    }
+                }
+            }
+            congruences = combined;
+            log::debug!("{} combined congruences", congruences.len());
         }
-    }
-    pub fn next(&mut self, name: &'a str) {
-        self.current = name;
+        congruences
+            .into_iter()
+            .map(|(rem, modulo)| {
+                // the smallest step with that residue once every ghost is in its cycle
+                let (rem, modulo) = (rem as u128, modulo as u128);
+                let cycles = (prefix as u128).saturating_sub(rem).div_ceil(modulo);
+                rem + cycles * modulo
+            })
+            .min()
+            .context("the ghosts never end up on end nodes at the same time")
     }
 }
 
-impl Debug for NodeHistory<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "done={}, end in z={:?}", self.done, self.end_in_z)
+struct Analysis<'a> {
+    /// node names of each strongly connected component, in topological order
+    components: Vec<Vec<&'a str>>,
+    /// for each start node, the end nodes it can reach
+    reachable_ends: Vec<(&'a str, Vec<&'a str>)>,
+    /// nodes from which no end node can be reached
+    dead: Vec<&'a str>,
+}
+
+/// The steps where one ghost is on an end node: every `prefix_ends` once,
+/// then every `cycle_ends` plus any multiple of `cycle_len`.
+#[derive(Debug)]
+struct GhostPath {
+    /// steps before the ghost enters its cycle
+    prefix: usize,
+    cycle_len: usize,
+    prefix_ends: Vec<usize>,
+    cycle_ends: Vec<usize>,
+}
+
+impl GhostPath {
+    pub fn ends_at(&self, step: usize) -> bool {
+        if step < self.prefix {
+            self.prefix_ends.contains(&step)
+        } else {
+            self.cycle_ends
+                .iter()
+                .any(|&end| step % self.cycle_len == end % self.cycle_len)
+        }
     }
 }
 
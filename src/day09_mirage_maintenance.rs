@@ -1,4 +1,5 @@
 use crate::common::day_setup::Day;
+use crate::common::sequence;
 use std::str::FromStr;
 
 pub fn day() -> Day {
@@ -9,7 +10,7 @@ pub fn day() -> Day {
 pub fn run(input: &str) {
     let histories: Vec<History> = input.lines().map(|line| line.parse().unwrap()).collect();
 
-    let sum: i64 = histories
+    let sum: i128 = histories
         .iter()
         .map(|history| {
             let next = history.next_value();
@@ -19,7 +20,7 @@ pub fn run(input: &str) {
         .sum();
     println!("sum part 1: {}", sum);
 
-    let sum: i64 = histories
+    let sum: i128 = histories
         .iter()
         .map(|history| {
             let previous = history.previous_value();
@@ -30,39 +31,14 @@ pub fn run(input: &str) {
     println!("sum part 2: {}", sum);
 }
 
-struct History(Vec<i64>);
+struct History(Vec<i128>);
 
 impl History {
-    pub fn next_value(&self) -> i64 {
-        let mut last_values = Vec::new();
-        let mut values = self.0.clone();
-        while values.iter().any(|&v| v != 0) {
-            assert!(values.len() > 1, "no values left for diff");
-            last_values.push(values.last().copied().unwrap());
-            values = values
-                .windows(2)
-                .map(|values| values[1] - values[0])
-                .collect();
-        }
-
-        last_values.iter().sum()
+    pub fn next_value(&self) -> i128 {
+        sequence::extrapolate(&self.0, 1).expect("cannot extrapolate history")
     }
-    pub fn previous_value(&self) -> i64 {
-        let mut first_values = Vec::new();
-        let mut values = self.0.clone();
-        while values.iter().any(|&v| v != 0) {
-            assert!(values.len() > 1, "no values left for diff");
-            first_values.push(values.first().copied().unwrap());
-            values = values
-                .windows(2)
-                .map(|values| values[1] - values[0])
-                .collect();
-        }
-
-        first_values.iter().rev().fold(0, |sum, &next| {
-            log::debug!("{} - {}", next, sum);
-            next - sum
-        })
+    pub fn previous_value(&self) -> i128 {
+        sequence::extrapolate(&self.0, -1).expect("cannot extrapolate history")
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::common::day_setup::Day;
 use crate::common::models::grid::GridLike;
 use crate::common::models::{Direction, DirectionFlag, Grid, Point};
+use crate::common::polygon;
 use anyhow::Context as AnyhowContext;
 use colored::Colorize;
 use std::collections::HashSet;
@@ -8,13 +9,16 @@ use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&[
-        "..F7.
+    Day::new(run).with_test_inputs(TEST_INPUTS)
+}
+
+const TEST_INPUTS: &[&str] = &[
+    "..F7.
 .FJ|.
 SJ.L7
 |F--J
 LJ...",
-        "...........
+    "...........
 .S-------7.
 .|F-----7|.
 .||.....||.
@@ -23,7 +27,7 @@ LJ...",
 .|..|.|..|.
 .L--J.L--J.
 ...........",
-        ".F----7F7F7F7F-7....
+    ".F----7F7F7F7F-7....
 .|F--7||||||||FJ....
 .||.FJ||||||||L7....
 FJL7L7LJLJ||LJ.L-7..
@@ -33,7 +37,7 @@ L--J.L7...LJS7F-7L7.
 .....|FJLJ|FJ|F7|.LJ
 ....FJL-7.||.||||...
 ....L---J.LJ.LJLJ...",
-        "FF7FSF7F7F7F7F7F---7
+    "FF7FSF7F7F7F7F7F---7
 L|LJ||||||||||||F--J
 FL-7LJLJ||||||LJL-77
 F--JF--7||LJLJ7F7FJ-
@@ -43,13 +47,64 @@ L---JF-JLJ.||-FJLJJ7
 7-L-JL7||F7|L7F-7F7|
 L.L7LFJ|||||FJL7||LJ
 L7JLJL-JLJLJL--JLJ.L",
-    ])
-}
+];
+
 pub fn run(input: &str) {
     let map: Map = input.parse::<InputMap>().unwrap().try_into().unwrap();
+    let main_loop = map.get_loop();
+
+    println!("part 1 - max steps: {}", main_loop.tiles.len() / 2);
+    println!(
+        "part 2 - enclosed tiles: {}",
+        main_loop.enclosed_tile_count()
+    );
+
+    let outside = map.squeeze_outside(&main_loop);
+    let on_loop: HashSet<_> = main_loop.tiles.iter().copied().collect();
+    let squeezed = map.squeezed_through(&main_loop, &outside);
+    log::debug!(
+        "{} tiles outside the loop, {} of them only by squeezing between pipes",
+        outside.len(),
+        squeezed.len()
+    );
+    println!(
+        "grid:\n{}",
+        map.grid.display_overriding(|point| {
+            let space = map.grid[point];
+            if &map.start_position == point {
+                Some(format!("{:#}", space).green())
+            } else if on_loop.contains(point) {
+                Some(format!("{:#}", space).blue())
+            } else if squeezed.contains(point) {
+                Some(space.to_string().yellow())
+            } else if !outside.contains(point) {
+                Some(space.to_string().red())
+            } else {
+                None
+            }
+        })
+    );
 
-    println!("part 1 - max steps: {}", map.get_loop().len() / 2);
-    println!("part 2 - enclosed tiles: {}", map.get_enclosed_tiles());
+    if log::log_enabled!(log::Level::Debug) {
+        let loops = map.find_loops();
+        log::debug!("{} closed loops in the grid", loops.len());
+        for pipe_loop in loops.iter() {
+            let on_loop: HashSet<_> = pipe_loop.tiles.iter().copied().collect();
+            log::debug!(
+                "loop through {} with {} tiles encloses {} tiles:{}",
+                pipe_loop.tiles[0],
+                pipe_loop.tiles.len(),
+                pipe_loop.enclosed_tile_count(),
+                map.grid.display_overriding(|point| {
+                    if on_loop.contains(point) {
+                        Some(format!("{:#}", map.grid[point]))
+                    } else {
+                        Some(" ".to_string())
+                    }
+                })
+            );
+        }
+    }
 }
 
 struct Map {
@@ -57,8 +112,30 @@ struct Map {
     start_position: Point<usize>,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// A closed pipe loop, its tiles in walking order.
+struct PipeLoop {
+    tiles: Vec<Point<usize>>,
+}
+
+impl PipeLoop {
+    /// Tiles fully inside the loop, by the shoelace formula and Pick's theorem
+    /// with the tile centers as the polygon's vertices.
+    pub fn enclosed_tile_count(&self) -> i64 {
+        let vertices: Vec<_> = self
+            .tiles
+            .iter()
+            .map(|tile| Point {
+                x: tile.x as i64,
+                y: tile.y as i64,
+            })
+            .collect();
+        polygon::interior_points(&vertices)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
 enum Space {
+    #[default]
     Empty,
     HorizontalPipe,
     VerticalPipe,
@@ -68,91 +145,138 @@ enum Space {
     SouthEastBend,
 }
 
-impl Default for Space {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 impl Map {
-    pub fn get_loop(&self) -> Vec<Point<usize>> {
-        const MAX_ITER: u64 = 10_000_000;
-        let mut entered_from = self.grid[&self.start_position].move_out_directions()[0];
-        let mut points = vec![self.start_position];
-        for i in 1..MAX_ITER {
-            let point = &points[points.len() - 1];
+    pub fn get_loop(&self) -> PipeLoop {
+        match self.follow_loop(self.start_position) {
+            Ok(pipe_loop) => pipe_loop,
+            Err(walked) => panic!("pipe closed after {} tiles", walked.len()),
+        }
+    }
+    /// Walks the pipes from `start` until they get back to it. On a dead end, returns
+    /// the tiles walked so far instead: none of them can be part of a loop.
+    fn follow_loop(&self, start: Point<usize>) -> Result<PipeLoop, Vec<Point<usize>>> {
+        if self.grid[&start] == Space::Empty {
+            return Err(vec![]);
+        }
+        // a loop cannot be longer than the whole grid
+        let max_len = self.grid.len_x() * self.grid.len_y();
+        let mut entered_from = self.grid[&start].move_out_directions()[0];
+        let mut tiles = vec![start];
+        while tiles.len() <= max_len {
+            let point = &tiles[tiles.len() - 1];
             let exit_to = self.grid[point].get_other_move_out_direction(entered_from);
             entered_from = exit_to.invert();
-            if let Some(next) = self
+            match self
                 .grid
                 .move_in_direction_if(point, exit_to, |(_, value)| {
                     value.can_enter_from(entered_from)
-                })
-            {
-                points.push(next);
-            } else {
-                panic!("pipe closed at i = {}", i);
+                }) {
+                Some(next) if next == start => return Ok(PipeLoop { tiles }),
+                Some(next) => tiles.push(next),
+                None => return Err(tiles),
             }
-            if points.last() == Some(&self.start_position) {
-                log::debug!("looped!");
-                return points;
+        }
+        unreachable!("pipes should either close or end within the grid");
+    }
+    /// Every closed loop in the grid, whether connected to the start or not.
+    pub fn find_loops(&self) -> Vec<PipeLoop> {
+        let mut visited: Grid<bool> = Grid::new(self.grid.len_x(), self.grid.len_y());
+        let mut loops = vec![];
+        for (point, _) in self.grid.iter() {
+            if visited[&point] {
+                continue;
+            }
+            let tiles = match self.follow_loop(point) {
+                Ok(pipe_loop) => {
+                    let tiles = pipe_loop.tiles.clone();
+                    loops.push(pipe_loop);
+                    tiles
+                }
+                Err(walked) => walked,
+            };
+            visited[&point] = true;
+            for tile in tiles {
+                visited[&tile] = true;
             }
         }
-        panic!("max iterations exceeded");
+        loops
     }
-    fn is_inside(&self, mut point: Point<usize>, part_of_loop: &HashSet<Point<usize>>) -> bool {
-        let mut crossings = 0;
-        while point.x < self.grid.len_x() {
-            point.x += 1;
-            if part_of_loop.contains(&point) {
-                match self.grid[&point] {
-                    Space::HorizontalPipe => {}
-                    Space::VerticalPipe => {
-                        crossings += 1;
-                    }
-                    Space::NorthEastBend | Space::NorthWestBend => {
-                        // (NE + NW) and (SE + SW) cancel out - can be counted 0 or 2 times
-                        // L-J F-7
-
-                        // (NE + SW) and (SE + NW) form 1 vertical line - must be counted once
-                        // L-7 F-J
-                    }
-                    Space::SouthWestBend | Space::SouthEastBend => {
-                        crossings += 1;
-                    }
-                    Space::Empty => unreachable!(),
+    /// The pipes of `pipe_loop` on a grid twice the size: tile (x, y) becomes (2x, 2y),
+    /// and the cells in between are walls only where the loop connects two tiles.
+    fn upscale(&self, pipe_loop: &PipeLoop) -> Grid<bool> {
+        let mut walls: Grid<bool> = Grid::new(self.grid.len_x() * 2, self.grid.len_y() * 2);
+        for tile in pipe_loop.tiles.iter() {
+            let doubled = Point {
+                x: tile.x * 2,
+                y: tile.y * 2,
+            };
+            walls[&doubled] = true;
+            for direction in self.grid[tile].move_out_directions() {
+                if let Some(between) = doubled.move_in(direction) {
+                    walls[&between] = true;
                 }
             }
         }
-        log::trace!("{} has {} crossings", point, crossings);
-        crossings % 2 == 1
+        walls
     }
-    pub fn get_enclosed_tiles(&self) -> usize {
-        let part_of_loop: HashSet<_> = self.get_loop().into_iter().collect();
-
-        let inside: HashSet<_> = self
-            .grid
+    /// Tiles not on the loop that can be reached from outside the grid,
+    /// squeezing between pipes that touch without connecting.
+    pub fn squeeze_outside(&self, pipe_loop: &PipeLoop) -> HashSet<Point<usize>> {
+        let walls = self.upscale(pipe_loop);
+        let mut reached: Grid<bool> = Grid::new(walls.len_x(), walls.len_y());
+        let mut stack: Vec<_> = walls
             .iter()
             .map(|(point, _)| point)
-            .filter(|point| !part_of_loop.contains(point))
-            .filter(|point| self.is_inside(*point, &part_of_loop))
+            .filter(|point| {
+                point.x == 0
+                    || point.y == 0
+                    || point.x == walls.len_x() - 1
+                    || point.y == walls.len_y() - 1
+            })
             .collect();
-
-        println!(
-            "grid:\n{}",
-            self.grid.display_overriding(|point| {
-                if &self.start_position == point {
-                    Some(self.grid[point].to_string().green())
-                } else if inside.contains(point) {
-                    Some(self.grid[point].to_string().red())
-                } else if part_of_loop.contains(point) {
-                    Some(self.grid[point].to_string().blue())
-                } else {
-                    None
-                }
+        while let Some(point) = stack.pop() {
+            if walls[&point] || std::mem::replace(&mut reached[&point], true) {
+                continue;
+            }
+            stack.extend(walls.neighbors(&point, DirectionFlag::FOUR_DIRECTIONS));
+        }
+        self.grid
+            .iter()
+            .map(|(point, _)| point)
+            .filter(|point| {
+                reached[&Point {
+                    x: point.x * 2,
+                    y: point.y * 2,
+                }]
+            })
+            .collect()
+    }
+    /// The `outside` tiles that are only reachable by squeezing between pipes,
+    /// not by walking over tiles off the loop.
+    pub fn squeezed_through(
+        &self,
+        pipe_loop: &PipeLoop,
+        outside: &HashSet<Point<usize>>,
+    ) -> HashSet<Point<usize>> {
+        let on_loop: HashSet<_> = pipe_loop.tiles.iter().copied().collect();
+        let mut walked = HashSet::new();
+        let mut stack: Vec<_> = outside
+            .iter()
+            .copied()
+            .filter(|point| {
+                point.x == 0
+                    || point.y == 0
+                    || point.x == self.grid.len_x() - 1
+                    || point.y == self.grid.len_y() - 1
             })
-        );
-        inside.len()
+            .collect();
+        while let Some(point) = stack.pop() {
+            if on_loop.contains(&point) || !walked.insert(point) {
+                continue;
+            }
+            stack.extend(self.grid.neighbors(&point, DirectionFlag::FOUR_DIRECTIONS));
+        }
+        outside.difference(&walked).copied().collect()
     }
 }
 
@@ -254,10 +378,10 @@ fn detect_space_type(point: &Point<usize>, map: &InputMap) -> anyhow::Result<Spa
         .into_iter()
         .filter_map(|neighbor| {
             let direction = Direction::from_vec(&neighbor, point).unwrap();
-            if let InputSpace::Space(space) = &map.0[&neighbor] {
-                if space.can_enter_from(direction) {
-                    return Some(direction.invert());
-                }
+            if let InputSpace::Space(space) = &map.0[&neighbor]
+                && space.can_enter_from(direction)
+            {
+                return Some(direction.invert());
             }
             None
         })
@@ -319,8 +443,21 @@ impl FromStr for InputMap {
     }
 }
 
+/// `{:#}` draws the pipes with box-drawing characters.
 impl Display for Space {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            let c = match self {
+                Space::Empty => ' ',
+                Space::HorizontalPipe => '─',
+                Space::VerticalPipe => '│',
+                Space::NorthEastBend => '└',
+                Space::NorthWestBend => '┘',
+                Space::SouthWestBend => '┐',
+                Space::SouthEastBend => '┌',
+            };
+            return write!(f, "{}", c);
+        }
         match self {
             Space::Empty => write!(f, "."),
             Space::HorizontalPipe => write!(f, "-"),
@@ -332,3 +469,25 @@ impl Display for Space {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_matches_squeeze() {
+        for input in TEST_INPUTS {
+            let map: Map = input.parse::<InputMap>().unwrap().try_into().unwrap();
+            let main_loop = map.get_loop();
+            let outside = map.squeeze_outside(&main_loop);
+            let enclosed =
+                map.grid.len_x() * map.grid.len_y() - main_loop.tiles.len() - outside.len();
+            assert_eq!(
+                main_loop.enclosed_tile_count(),
+                enclosed as i64,
+                "{}",
+                input
+            );
+        }
+    }
+}
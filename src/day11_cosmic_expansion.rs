@@ -1,11 +1,11 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
 use crate::common::models::Point;
-use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use anyhow::Context as AnyhowContext;
+use std::ops::Range;
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["...#......
+    Day::custom(run).with_test_inputs(&["...#......
 .......#..
 #.........
 ..........
@@ -16,62 +16,238 @@ pub fn day() -> Day {
 .......#..
 #...#....."])
 }
-pub fn run(input: &str) {
-    let mut universe: Universe = input.parse().unwrap();
-    println!("part 1 sum of lengths: {}", universe.sum_distances());
-    //universe.set_expansion(10);
-    universe.set_expansion(1_000_000);
+/// Extra arguments like `x=10`, `y=5` or `x[3..7]=100` expand the universe
+/// once more with those factors, on top of both parts.
+pub fn run(context: &AppContext) {
+    let universe: Universe = context.get_input().parse().unwrap();
+    let expanded = universe.expand(&Expansion::uniform(2), &Expansion::uniform(2));
+    println!("part 1 sum of lengths: {}", expanded.sum_distances());
+
+    let expansion = Expansion::uniform(1_000_000);
+    let expanded = universe.expand(&expansion, &expansion);
     println!(
         "part 2 sum of lengths with expansion {}: {}",
-        universe.get_expansion(),
-        universe.sum_distances()
+        expansion.default,
+        expanded.sum_distances()
     );
+    let galaxies = 0..expanded.galaxies.len();
+    let nearest = galaxies
+        .clone()
+        .filter_map(|a| Some((a, expanded.nearest(a)?)));
+    if let Some((a, (b, distance))) = nearest.min_by_key(|&(a, (_, distance))| (distance, a)) {
+        log::debug!(
+            "closest galaxies: {} and {}, {} apart",
+            a + 1,
+            b + 1,
+            distance
+        );
+    }
+    let farthest = galaxies.filter_map(|a| Some((a, expanded.farthest(a)?)));
+    if let Some((a, (b, distance))) = farthest.max_by_key(|&(_, (_, distance))| distance) {
+        log::debug!(
+            "farthest galaxies: {} and {}, {} apart",
+            a + 1,
+            b + 1,
+            distance
+        );
+    }
+
+    if !context.args().is_empty() {
+        let (mut x, mut y) = (Expansion::uniform(2), Expansion::uniform(2));
+        for arg in context.args() {
+            let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+            let (axis, range) = match name.strip_suffix(']').and_then(|name| name.split_once('[')) {
+                Some((axis, range)) => (axis, Some(parse_range(range).unwrap())),
+                None => (name, None),
+            };
+            let expansion = match axis {
+                "x" => &mut x,
+                "y" => &mut y,
+                _ => panic!("unknown argument '{}'", name),
+            };
+            let factor = value.parse().expect("invalid factor");
+            match range {
+                Some(range) => expansion.regions.push((range, factor)),
+                None => expansion.default = factor,
+            }
+        }
+        let expanded = universe.expand(&x, &y);
+        println!(
+            "sum of lengths with expansion x: {:?}, y: {:?}: {}",
+            x,
+            y,
+            expanded.sum_distances()
+        );
+    }
+}
+
+/// `<start>..<end>`
+fn parse_range(range: &str) -> anyhow::Result<Range<usize>> {
+    let (start, end) = range
+        .split_once("..")
+        .with_context(|| format!("invalid range '{}'", range))?;
+    Ok(start.parse()?..end.parse()?)
 }
 
+/// How many lines every empty line becomes, along one axis.
+#[derive(Debug, Clone)]
+struct Expansion {
+    default: u64,
+    /// factors for the empty lines in a range of coordinates, later regions win
+    regions: Vec<(Range<usize>, u64)>,
+}
+
+impl Expansion {
+    pub fn uniform(factor: u64) -> Self {
+        Self {
+            default: factor,
+            regions: vec![],
+        }
+    }
+    pub fn factor_at(&self, coordinate: usize) -> u64 {
+        self.regions
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&coordinate))
+            .map_or(self.default, |&(_, factor)| factor)
+    }
+    /// Where every coordinate up to `len` ends up, as prefix sums over the lines before it.
+    fn positions(&self, len: usize, is_empty: &[bool]) -> Vec<u64> {
+        let mut positions = Vec::with_capacity(len);
+        let mut position = 0;
+        for (coordinate, &empty) in is_empty.iter().enumerate().take(len) {
+            positions.push(position);
+            position += if empty { self.factor_at(coordinate) } else { 1 };
+        }
+        positions
+    }
+}
+
+/// Galaxies in reading order, and which rows and columns have none.
 struct Universe {
-    x_y: HashMap<usize, HashSet<usize>>,
-    y_x: HashMap<usize, HashSet<usize>>,
-    expansion: usize,
+    galaxies: Vec<Point<usize>>,
+    empty_columns: Vec<bool>,
+    empty_rows: Vec<bool>,
 }
 
 impl Universe {
-    fn new(galaxies: impl Iterator<Item = Point<usize>>) -> Self {
-        let mut x_y: HashMap<usize, HashSet<usize>> = Default::default();
-        let mut y_x: HashMap<usize, HashSet<usize>> = Default::default();
-        for Point { x, y } in galaxies {
-            x_y.entry(x).or_default().insert(y);
-            y_x.entry(y).or_default().insert(x);
+    fn new(galaxies: Vec<Point<usize>>, len_x: usize, len_y: usize) -> Self {
+        let mut empty_columns = vec![true; len_x];
+        let mut empty_rows = vec![true; len_y];
+        for galaxy in galaxies.iter() {
+            empty_columns[galaxy.x] = false;
+            empty_rows[galaxy.y] = false;
         }
         Self {
-            x_y,
-            y_x,
-            expansion: 2,
+            galaxies,
+            empty_columns,
+            empty_rows,
         }
     }
-    pub fn set_expansion(&mut self, new_expansion: usize) {
-        self.expansion = new_expansion;
+    pub fn expand(&self, x: &Expansion, y: &Expansion) -> ExpandedUniverse {
+        let xs = x.positions(self.empty_columns.len(), &self.empty_columns);
+        let ys = y.positions(self.empty_rows.len(), &self.empty_rows);
+        ExpandedUniverse::new(
+            self.galaxies
+                .iter()
+                .map(|galaxy| Point {
+                    x: xs[galaxy.x],
+                    y: ys[galaxy.y],
+                })
+                .collect(),
+        )
     }
-    pub fn get_expansion(&self) -> usize {
-        self.expansion
+}
+
+/// Galaxies are referred to by their index in reading order.
+struct ExpandedUniverse {
+    galaxies: Vec<Point<u64>>,
+    /// galaxy indices sorted by x
+    by_x: Vec<usize>,
+    /// the galaxies with the smallest and biggest x + y and x - y
+    extremes: Vec<usize>,
+}
+
+impl ExpandedUniverse {
+    fn new(galaxies: Vec<Point<u64>>) -> Self {
+        let mut by_x: Vec<_> = (0..galaxies.len()).collect();
+        by_x.sort_unstable_by_key(|&i| galaxies[i].x);
+        let sum = |i: &usize| galaxies[*i].x + galaxies[*i].y;
+        let difference = |i: &usize| galaxies[*i].x as i64 - galaxies[*i].y as i64;
+        let indices = || 0..galaxies.len();
+        let extremes = [
+            indices().min_by_key(sum),
+            indices().max_by_key(sum),
+            indices().min_by_key(difference),
+            indices().max_by_key(difference),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        Self {
+            galaxies,
+            by_x,
+            extremes,
+        }
+    }
+    pub fn distance(&self, a: usize, b: usize) -> u64 {
+        let (a, b) = (&self.galaxies[a], &self.galaxies[b]);
+        a.x.abs_diff(b.x) + a.y.abs_diff(b.y)
     }
-    fn calculate_distance(&self, from: &Point<usize>, to: &Point<usize>) -> usize {
-        from.manhattan_distance(to)
-            + (from.x.min(to.x) + 1..from.x.max(to.x))
-                .filter(|x| !self.x_y.contains_key(x))
-                .count()
-                * (self.expansion - 1)
-            + (from.y.min(to.y) + 1..from.y.max(to.y))
-                .filter(|y| !self.y_x.contains_key(y))
-                .count()
-                * (self.expansion - 1)
+    /// Manhattan distances split by axis: every coordinate is added once for each
+    /// smaller coordinate and subtracted once for each larger one.
+    pub fn sum_distances(&self) -> u64 {
+        fn sum_axis(mut values: Vec<u64>) -> u64 {
+            values.sort_unstable();
+            let mut before = 0;
+            let mut sum = 0;
+            for (i, &value) in values.iter().enumerate() {
+                sum += value * i as u64 - before;
+                before += value;
+            }
+            sum
+        }
+        sum_axis(self.galaxies.iter().map(|galaxy| galaxy.x).collect())
+            + sum_axis(self.galaxies.iter().map(|galaxy| galaxy.y).collect())
     }
-    pub fn sum_distances(&self) -> usize {
-        self.x_y
+    /// The closest other galaxy and its distance, lowest index on ties. Walks away from `a`
+    /// in x order both ways, until the x distance alone is worse than the best so far.
+    pub fn nearest(&self, a: usize) -> Option<(usize, u64)> {
+        let position = self
+            .by_x
+            .partition_point(|&b| self.galaxies[b].x < self.galaxies[a].x);
+        let mut best: Option<(usize, u64)> = None;
+        let is_better = |best: Option<(usize, u64)>, candidate: (usize, u64)| {
+            best.is_none_or(|best| (candidate.1, candidate.0) < (best.1, best.0))
+        };
+        for &b in self.by_x[position..].iter() {
+            let dx = self.galaxies[b].x - self.galaxies[a].x;
+            if best.is_some_and(|(_, distance)| dx > distance) {
+                break;
+            }
+            if b != a && is_better(best, (b, self.distance(a, b))) {
+                best = Some((b, self.distance(a, b)));
+            }
+        }
+        for &b in self.by_x[..position].iter().rev() {
+            let dx = self.galaxies[a].x - self.galaxies[b].x;
+            if best.is_some_and(|(_, distance)| dx > distance) {
+                break;
+            }
+            if is_better(best, (b, self.distance(a, b))) {
+                best = Some((b, self.distance(a, b)));
+            }
+        }
+        best
+    }
+    /// The galaxy farthest away and its distance. In coordinates rotated by 45 degrees the
+    /// distance is the bigger of the two axis differences, so it is one of the 4 extremes.
+    pub fn farthest(&self, a: usize) -> Option<(usize, u64)> {
+        self.extremes
             .iter()
-            .flat_map(|(&x, ys)| ys.iter().map(move |&y| Point { x, y }))
-            .combinations(2)
-            .map(|items| self.calculate_distance(&items[0], &items[1]))
-            .sum()
+            .filter(|&&b| b != a)
+            .map(|&b| (b, self.distance(a, b)))
+            .max_by_key(|&(b, distance)| (distance, std::cmp::Reverse(b)))
     }
 }
 
@@ -79,12 +255,21 @@ impl FromStr for Universe {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self::new(s.lines().enumerate().flat_map(|(y, line)| {
-            line.chars().enumerate().filter_map(
-                move |(x, c)| {
-                    if c == '#' { Some(Point { x, y }) } else { None }
-                },
-            )
-        })))
+        let len_y = s.lines().count();
+        let len_x = s.lines().next().map_or(0, |line| line.len());
+        let mut galaxies = vec![];
+        for (y, line) in s.lines().enumerate() {
+            if line.len() != len_x {
+                return Err(anyhow::anyhow!("line {} has a different length", y + 1));
+            }
+            for (x, c) in line.chars().enumerate() {
+                match c {
+                    '#' => galaxies.push(Point { x, y }),
+                    '.' => {}
+                    other => return Err(anyhow::anyhow!("invalid character '{}'", other)),
+                }
+            }
+        }
+        Ok(Self::new(galaxies, len_x, len_y))
     }
 }
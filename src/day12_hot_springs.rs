@@ -1,171 +1,143 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
+use crate::common::models::Grid;
+use crate::common::models::grid::GridLike;
+use crate::common::nonogram::{LineSolver, Puzzle, Solution};
+use crate::common::random::Rng;
 use anyhow::Context as AnyhowContext;
 use itertools::Itertools;
-use std::borrow::Cow;
-use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["???.### 1,1,3
+    Day::custom(run).with_test_inputs(&["???.### 1,1,3
 .??..??...?##. 1,1,3
 ?#?#?#?#?#?#?#? 1,3,1,6
 ????.#...#... 4,1,1
 ????.######..#####. 1,6,5
 ?###???????? 3,2,1"])
 }
-pub fn run(input: &str) {
-    let rows: Vec<Row> = input.lines().map(|line| line.parse().unwrap()).collect();
+/// Takes `unfold=<n>` to change how many copies part 2 unfolds to, `list=<n>` to show
+/// the first arrangements of every row, and `seed=<n>` to show a random one of each.
+/// `nonogram=<file>` solves a full nonogram puzzle with the same line solver.
+pub fn run(context: &AppContext) {
+    let rows: Vec<Row> = context
+        .get_input()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect();
+    let mut unfold = 5;
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "nonogram" => solve_nonogram(value),
+            "unfold" => unfold = value.parse().expect("invalid number of copies"),
+            "list" => {
+                let count = value.parse().expect("invalid number of arrangements");
+                for row in rows.iter() {
+                    println!("{}:", row);
+                    for arrangement in row.solver().arrangements(count) {
+                        println!("  {}", render(&arrangement));
+                    }
+                }
+            }
+            "seed" => {
+                let mut rng = Rng::seeded(value.parse().expect("invalid seed"));
+                for row in rows.iter() {
+                    match row.solver().sample(&mut rng) {
+                        Some(arrangement) => println!("{}: {}", row, render(&arrangement)),
+                        None => println!("{}: impossible", row),
+                    }
+                }
+            }
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+
     Row::print_arrangements(&rows, 1);
-    let rows = {
-        let mut rows = rows;
-        rows.iter_mut().for_each(|row| row.unfold());
-        rows
-    };
+    let rows: Vec<_> = rows.iter().map(|row| row.unfold(unfold)).collect();
     Row::print_arrangements(&rows, 2);
 }
 
+fn solve_nonogram(path: &str) {
+    let puzzle: Puzzle = std::fs::read_to_string(path)
+        .expect("cannot read the puzzle")
+        .parse()
+        .unwrap();
+    let render = |grid: &Grid<bool>| {
+        grid.display_with_rule(|(_, &filled)| if filled { '█' } else { '·' })
+            .to_string()
+    };
+    match puzzle.solve() {
+        Solution::Impossible => println!("the nonogram has no solution"),
+        Solution::Unique(grid) => println!("the nonogram has a unique solution:{}", render(&grid)),
+        Solution::Multiple(first, second) => println!(
+            "the nonogram has multiple solutions, for example:{}\nand:{}",
+            render(&first),
+            render(&second)
+        ),
+    }
+}
+
+fn render(arrangement: &[bool]) -> String {
+    arrangement
+        .iter()
+        .map(|&damaged| if damaged { '#' } else { '.' })
+        .collect()
+}
+
 #[derive(Clone)]
 struct Conditions(Vec<Option<bool>>);
 #[derive(Clone)]
-struct DamagedGroups(Vec<u8>);
+struct DamagedGroups(Vec<usize>);
 
 #[derive(Clone)]
 struct Row {
     conditions: Conditions,
-    damaged_groups_rev: DamagedGroups,
+    damaged_groups: DamagedGroups,
 }
 
 impl Row {
     pub fn print_arrangements(values: &[Self], part: usize) {
-        let mut cache = HashMap::new();
         println!(
             "part {} possible arrangements: {}",
             part,
             values
                 .iter()
                 .map(|row| {
-                    let count = row.possible_arrangements(&mut cache);
+                    let solver = row.solver();
+                    let count = solver.count();
                     log::debug!("{} - {} arrangements", row, count);
+                    if log::log_enabled!(log::Level::Debug) {
+                        let forced: String = row
+                            .conditions
+                            .0
+                            .iter()
+                            .zip(solver.forced())
+                            .map(|(condition, forced)| match (condition, forced) {
+                                (None, Some(true)) => '#',
+                                (None, Some(false)) => '.',
+                                _ => ' ',
+                            })
+                            .collect();
+                        if !forced.trim().is_empty() {
+                            log::debug!("{}   forced unknown cells", forced);
+                        }
+                    }
                     count
                 })
-                .sum::<usize>()
+                .sum::<u128>()
         );
     }
-    pub fn unfold(&mut self) {
-        let mut conditions = vec![None; (self.conditions.0.len() + 1) * 5 - 1];
-        for (i, &condition) in self.conditions.0.iter().enumerate() {
-            for j in 0..5 {
-                conditions[i + (self.conditions.0.len() + 1) * j] = condition;
-            }
-        }
-        let mut damaged = vec![0; self.damaged_groups_rev.0.len() * 5];
-        for (i, &value) in self.damaged_groups_rev.0.iter().enumerate() {
-            for j in 0..5 {
-                damaged[i + self.damaged_groups_rev.0.len() * j] = value;
-            }
-        }
-
-        self.conditions = Conditions(conditions);
-        self.damaged_groups_rev = DamagedGroups(damaged);
+    pub fn solver(&self) -> LineSolver<'_> {
+        LineSolver::new(&self.conditions.0, &self.damaged_groups.0)
     }
-    pub fn possible_arrangements(&self, cache: &mut HashMap<CacheIndex, usize>) -> usize {
-        self.possible_arrangements_recurse(0, cache)
-    }
-    fn no_damaged_after(&self, i: usize) -> bool {
-        !self.conditions.0.iter().skip(i).any(|&c| c == Some(true))
-    }
-    fn possible_arrangements_recurse(
-        &self,
-        i: usize,
-        cache: &mut HashMap<CacheIndex, usize>,
-    ) -> usize {
-        if i >= self.conditions.0.len() {
-            return if self.damaged_groups_rev.0.is_empty() {
-                log::trace!("{} is possible v1", self);
-                1
-            } else {
-                0
-            };
-        }
-        if self.damaged_groups_rev.0.is_empty() {
-            return if self.no_damaged_after(i) {
-                log::trace!("{} is possible v2", self);
-                1
-            } else {
-                0
-            };
+    /// `times` copies of the row, the conditions joined by unknown cells.
+    pub fn unfold(&self, times: usize) -> Self {
+        let conditions = vec![self.conditions.0.clone(); times].join(&None);
+        Self {
+            conditions: Conditions(conditions),
+            damaged_groups: DamagedGroups(self.damaged_groups.0.repeat(times)),
         }
-        if let Some(&result) = cache.get(&CacheIndex::new_borrowed(self, i)) {
-            return result;
-        }
-
-        let mut sum = 0;
-        if let Some((count, next)) = self.with_next_damaged(i) {
-            sum += next.possible_arrangements_recurse(i + count, cache);
-        }
-        if let Some(next) = self.with_next_undamaged(i) {
-            sum += next.possible_arrangements_recurse(i + 1, cache);
-        }
-        cache.insert(CacheIndex::new_owned(self, i), sum);
-        sum
-    }
-    fn with_next_undamaged(&self, i: usize) -> Option<Cow<'_, Self>> {
-        match self.conditions.0[i] {
-            Some(true) => None,
-            Some(false) => Some(Cow::Borrowed(self)),
-            None => {
-                let mut next = self.clone();
-                next.conditions.0[i] = Some(false);
-                Some(Cow::Owned(next))
-            }
-        }
-    }
-    fn with_next_damaged(&self, i: usize) -> Option<(usize, Self)> {
-        let count = if let Some(count) = self.damaged_groups_rev.0.last() {
-            *count as usize
-        } else {
-            return None;
-        };
-
-        if (i + count > self.conditions.0.len())
-            || (i != 0 && self.conditions.0[i - 1] == Some(true))
-            || (i + count < self.conditions.0.len() && self.conditions.0[i + count] == Some(true))
-        {
-            return None;
-        }
-
-        let mut next = self.clone();
-        next.damaged_groups_rev.0.pop();
-        for value in next.conditions.0[i..i + count].iter_mut() {
-            if *value == Some(false) {
-                return None;
-            }
-            *value = Some(true);
-        }
-
-        Some((count, next))
-    }
-}
-
-#[derive(Eq, PartialEq, Hash)]
-struct CacheIndex<'a>(Cow<'a, [Option<bool>]>, Cow<'a, [u8]>);
-
-impl<'a> CacheIndex<'a> {
-    pub fn new_borrowed(row: &'a Row, i: usize) -> Self {
-        Self(
-            Cow::Borrowed(&row.conditions.0[i.saturating_sub(1)..]),
-            Cow::Borrowed(&row.damaged_groups_rev.0),
-        )
-    }
-}
-
-impl CacheIndex<'static> {
-    pub fn new_owned(row: &Row, i: usize) -> Self {
-        Self(
-            Cow::Owned(row.conditions.0[i.saturating_sub(1)..].to_vec()),
-            Cow::Owned(row.damaged_groups_rev.0.clone()),
-        )
     }
 }
 
@@ -189,13 +161,12 @@ impl FromStr for Row {
                 })
                 .collect::<Result<Vec<_>, _>>()?,
         );
-        let damaged_groups_rev = DamagedGroups(
+        let damaged_groups = DamagedGroups(
             parts
                 .next()
                 .context("no damaged groups")?
                 .split(',')
                 .map(|s| s.parse())
-                .rev()
                 .collect::<Result<Vec<_>, _>>()?,
         );
         if let Some(next) = parts.next() {
@@ -204,7 +175,7 @@ impl FromStr for Row {
 
         Ok(Self {
             conditions,
-            damaged_groups_rev,
+            damaged_groups,
         })
     }
 }
@@ -223,10 +194,9 @@ impl Display for Row {
                     None => '?',
                 })
                 .join(""),
-            self.damaged_groups_rev
+            self.damaged_groups
                 .0
                 .iter()
-                .rev()
                 .map(|val| val.to_string())
                 .join(",")
         )
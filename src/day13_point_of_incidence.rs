@@ -1,10 +1,13 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
 use crate::common::models::grid::GridLike;
 use crate::common::models::{Grid, Point};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["#.##..##.
+    Day::custom(run).with_test_inputs(&["#.##..##.
 ..#.##.#.
 ##......#
 ##......#
@@ -20,135 +23,249 @@ pub fn day() -> Day {
 ..##..###
 #....#..#"])
 }
-pub fn run(input: &str) {
+/// `smudges=<k>` lists every reflection with exactly `k` smudges in each pattern,
+/// `diagonals=true` also looks for mirrors along the diagonals of square patterns.
+pub fn run(context: &AppContext) {
+    let input = context.get_input();
     let patterns: Vec<Pattern> = input
+        .as_str()
         .split("\n\n")
         .map(|chunk| chunk.parse().unwrap())
         .collect();
-    let summary: usize = patterns
-        .iter()
-        .map(|pattern| pattern.get_split())
-        .map(|split| split.summary())
-        .sum();
-    println!("part 1 summary: {}", summary);
-
-    let summary: usize = patterns
-        .iter()
-        .map(|pattern| pattern.split_with_fixed_smudge())
-        .map(|split| split.summary())
-        .sum();
-    println!("part 2 summary: {}", summary);
+    println!("part 1 summary: {}", summarize(&patterns, 0));
+    println!("part 2 summary: {}", summarize(&patterns, 1));
+
+    let (mut smudges, mut diagonals) = (None, false);
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "smudges" => smudges = Some(value.parse().expect("invalid number of smudges")),
+            "diagonals" => diagonals = value.parse().expect("expected diagonals=<true|false>"),
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+    if let Some(smudges) = smudges {
+        for (i, pattern) in patterns.iter().enumerate() {
+            for reflection in pattern.reflections(smudges, diagonals) {
+                println!(
+                    "pattern {}: {}{}",
+                    i + 1,
+                    reflection,
+                    pattern.render(&reflection)
+                );
+            }
+        }
+    }
+}
+
+/// Patterns without exactly one reflection get reported, and left out of the summary.
+fn summarize(patterns: &[Pattern], smudges: usize) -> usize {
+    let mut summary = 0;
+    for (i, pattern) in patterns.iter().enumerate() {
+        match pattern.find_reflection(smudges, false) {
+            Ok(reflection) => {
+                log::debug!("{}{}", reflection, pattern.render(&reflection));
+                summary += reflection.split.summary().unwrap_or_default();
+            }
+            Err(error) => log::error!("pattern {}: {}{}", i + 1, error, pattern.display()),
+        }
+    }
+    summary
 }
 
 struct Pattern(Grid<bool>);
 
-impl Pattern {
-    pub fn split_with_fixed_smudge(&self) -> Split {
-        let splits: Vec<_> = (1..self.0.len_x())
-            .filter_map(|x| self.try_fix_smudge(x, false).map(|_| Split::Vertical(x)))
-            .chain(
-                (1..self.0.len_y())
-                    .filter_map(|y| self.try_fix_smudge(y, true).map(|_| Split::Horizontal(y))),
-            )
-            .collect();
-        self.output_split(splits)
-    }
-    fn output_split(&self, splits: Vec<Split>) -> Split {
-        if splits.len() != 1 {
-            panic!(
-                "found {} splits for {}",
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Reflection {
+    split: Split,
+    /// the cells to flip for a perfect reflection, on the top or left side of the mirror
+    smudges: Vec<Point<usize>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ReflectionError {
+    Missing { smudges: usize },
+    Ambiguous { smudges: usize, splits: Vec<Split> },
+}
+
+impl Display for ReflectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectionError::Missing { smudges } => {
+                write!(f, "no reflection with exactly {} smudges", smudges)
+            }
+            ReflectionError::Ambiguous { smudges, splits } => write!(
+                f,
+                "{} reflections with exactly {} smudges: {:?}",
                 splits.len(),
-                self.0
-                    .display_overriding(|point| { Some(if self.0[point] { '#' } else { '.' }) })
-            );
+                smudges,
+                splits
+            ),
         }
-        splits.into_iter().next().unwrap()
     }
-    pub fn get_split(&self) -> Split {
-        let splits: Vec<_> = (1..self.0.len_x())
-            .filter_map(|x| {
-                if self.is_vertical_split(x) {
-                    Some(Split::Vertical(x))
-                } else {
-                    None
+}
+
+impl std::error::Error for ReflectionError {}
+
+impl Pattern {
+    /// The only reflection with exactly `smudges` mismatching cells.
+    pub fn find_reflection(
+        &self,
+        smudges: usize,
+        diagonals: bool,
+    ) -> Result<Reflection, ReflectionError> {
+        let mut reflections = self.reflections(smudges, diagonals);
+        match reflections.len() {
+            0 => Err(ReflectionError::Missing { smudges }),
+            1 => Ok(reflections.pop().unwrap()),
+            _ => Err(ReflectionError::Ambiguous {
+                smudges,
+                splits: reflections
+                    .into_iter()
+                    .map(|reflection| reflection.split)
+                    .collect(),
+            }),
+        }
+    }
+    /// Every reflection with exactly `smudges` mismatching cells. Diagonal mirrors
+    /// only exist for square patterns.
+    pub fn reflections(&self, smudges: usize, diagonals: bool) -> Vec<Reflection> {
+        let (len_x, len_y) = (self.0.len_x(), self.0.len_y());
+        let mut splits: Vec<_> = (1..len_x)
+            .map(Split::Vertical)
+            .chain((1..len_y).map(Split::Horizontal))
+            .collect();
+        if diagonals && len_x == len_y {
+            splits.extend([Split::Diagonal, Split::AntiDiagonal]);
+        }
+        splits
+            .into_iter()
+            .filter_map(|split| {
+                let mut found = vec![];
+                for (a, b) in split.pairs(len_x, len_y) {
+                    if self.0[&a] != self.0[&b] {
+                        if found.len() == smudges {
+                            return None;
+                        }
+                        found.push(a);
+                    }
                 }
+                (found.len() == smudges).then_some(Reflection {
+                    split,
+                    smudges: found,
+                })
             })
-            .chain((1..self.0.len_y()).filter_map(|y| {
-                if self.is_horizontal_split(y) {
-                    Some(Split::Horizontal(y))
+            .collect()
+    }
+    /// The pattern with the cells along the mirror in blue and the smudges in red.
+    pub fn render(&self, reflection: &Reflection) -> String {
+        let smudges: HashSet<_> = reflection.smudges.iter().collect();
+        let n = self.0.len_x();
+        self.0
+            .display_overriding(|point| {
+                let c = if self.0[point] { "#" } else { "." };
+                let on_mirror = match reflection.split {
+                    Split::Vertical(x) => point.x + 1 == x || point.x == x,
+                    Split::Horizontal(y) => point.y + 1 == y || point.y == y,
+                    Split::Diagonal => point.x == point.y,
+                    Split::AntiDiagonal => point.x + point.y + 1 == n,
+                };
+                if smudges.contains(point) {
+                    Some(c.red().bold())
+                } else if on_mirror {
+                    Some(c.blue())
                 } else {
-                    None
+                    Some(c.normal())
                 }
-            }))
-            .collect();
-        self.output_split(splits)
-    }
-    fn is_vertical_split(&self, split: usize) -> bool {
-        (0..split)
-            .rev()
-            .zip(split..self.0.len_x())
-            .all(|(left, right)| (0..self.0.len_y()).all(|y| self.0[y][left] == self.0[y][right]))
-    }
-    fn is_horizontal_split(&self, split: usize) -> bool {
-        (0..split)
-            .rev()
-            .zip(split..self.0.len_y())
-            .all(|(left, right)| (0..self.0.len_x()).all(|x| self.0[left][x] == self.0[right][x]))
+            })
+            .to_string()
     }
-    fn try_fix_smudge(&self, split: usize, is_horizontal: bool) -> Option<Point<usize>> {
-        let (max_lr, max_z) = if is_horizontal {
-            (self.0.len_y(), self.0.len_x())
-        } else {
-            (self.0.len_x(), self.0.len_y())
-        };
-        let get = |left_right: usize, z: usize| -> bool {
-            if is_horizontal {
-                self.0[left_right][z]
-            } else {
-                self.0[z][left_right]
-            }
-        };
-
-        let mut smudge = None;
-        for (left, right) in (0..split).rev().zip(split..max_lr) {
-            let mut wrong = None;
-            for z in 0..max_z {
-                if get(left, z) != get(right, z) {
-                    if wrong.is_some() {
-                        return None;
-                    }
-                    wrong = Some(z);
-                }
-            }
-            if let Some(z) = wrong {
-                if smudge.is_some() {
-                    return None;
-                }
-                smudge = if is_horizontal {
-                    Some(Point { x: z, y: left })
-                } else {
-                    Some(Point { x: left, y: z })
-                };
-            }
-        }
-
-        smudge
+    fn display(&self) -> String {
+        self.0
+            .display_overriding(|point| Some(if self.0[point] { '#' } else { '.' }))
+            .to_string()
     }
 }
 
-#[derive(Debug)]
+/// Where the mirror sits. Vertical and horizontal mirrors go between the columns or rows
+/// before and after the index, diagonal mirrors go through the corners of a square pattern.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 enum Split {
     Horizontal(usize),
     Vertical(usize),
+    /// top left to bottom right
+    Diagonal,
+    /// top right to bottom left
+    AntiDiagonal,
 }
 
 impl Split {
-    pub fn summary(&self) -> usize {
-        log::debug!("split: {:?}", self);
+    /// Diagonal mirrors are not part of the puzzle, so they have no summary.
+    pub fn summary(&self) -> Option<usize> {
         match self {
-            Split::Horizontal(val) => 100 * *val,
-            Split::Vertical(val) => *val,
+            Split::Horizontal(val) => Some(100 * *val),
+            Split::Vertical(val) => Some(*val),
+            Split::Diagonal | Split::AntiDiagonal => None,
+        }
+    }
+    /// The pairs of cells the mirror reflects onto each other, each pair once.
+    fn pairs(&self, len_x: usize, len_y: usize) -> Vec<(Point<usize>, Point<usize>)> {
+        match *self {
+            Split::Vertical(split) => (0..split)
+                .rev()
+                .zip(split..len_x)
+                .flat_map(|(left, right)| {
+                    (0..len_y).map(move |y| (Point { x: left, y }, Point { x: right, y }))
+                })
+                .collect(),
+            Split::Horizontal(split) => (0..split)
+                .rev()
+                .zip(split..len_y)
+                .flat_map(|(top, bottom)| {
+                    (0..len_x).map(move |x| (Point { x, y: top }, Point { x, y: bottom }))
+                })
+                .collect(),
+            Split::Diagonal => (0..len_y)
+                .flat_map(|y| (y + 1..len_x).map(move |x| (Point { x, y }, Point { x: y, y: x })))
+                .collect(),
+            Split::AntiDiagonal => (0..len_y)
+                .flat_map(|y| {
+                    (0..len_x - 1 - y).map(move |x| {
+                        (
+                            Point { x, y },
+                            Point {
+                                x: len_x - 1 - y,
+                                y: len_y - 1 - x,
+                            },
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Split {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Split::Horizontal(y) => write!(f, "horizontal mirror above row {}", y + 1),
+            Split::Vertical(x) => write!(f, "vertical mirror left of column {}", x + 1),
+            Split::Diagonal => write!(f, "diagonal mirror from the top left"),
+            Split::AntiDiagonal => write!(f, "diagonal mirror from the top right"),
+        }
+    }
+}
+
+impl Display for Reflection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.split)?;
+        if !self.smudges.is_empty() {
+            write!(f, " with smudges at")?;
+            for smudge in self.smudges.iter() {
+                write!(f, " {}", smudge)?;
+            }
         }
+        Ok(())
     }
 }
 
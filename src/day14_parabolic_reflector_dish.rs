@@ -1,11 +1,11 @@
-use crate::common::day_setup::Day;
-use crate::common::models::grid::GridLike;
-use crate::common::models::{Direction, Grid, Point};
-use std::collections::{HashMap, VecDeque};
+use crate::common::day_setup::{AppContext, Day};
+use crate::common::models::Direction;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["O....#....
+    Day::custom(run).with_test_inputs(&["O....#....
 O.OO#....#
 .....##...
 OO.#O....O
@@ -16,180 +16,213 @@ O.#..O.#.#
 #....###..
 #OO..#...."])
 }
-pub fn run(input: &str) {
-    let mut platform: Platform = input.parse().unwrap();
-    log::debug!(
-        "pre-tilt:\n{}",
-        platform.grid.display_with_rule(display_grid)
-    );
-    platform.tilt(Direction::Up);
-    log::debug!(
-        "post-tilt:\n{}",
-        platform.grid.display_with_rule(display_grid)
-    );
-    println!("part 1 total load: {}", platform.total_load());
+/// Takes `spin=<directions>` (like the default `ULDR`), `cycles=<n>`, `load=<direction>`
+/// and `export=<file>` to look at the platform after any number of any spin cycles.
+pub fn run(context: &AppContext) {
+    let platform: Platform = context.get_input().parse().unwrap();
+    log::debug!("pre-tilt:\n{}", platform);
+    let tilted = Spinner::new(&platform, &[Direction::Up]).after(&platform, 1);
+    log::debug!("post-tilt:\n{}", tilted);
+    println!("part 1 total load: {}", tilted.load(Direction::Up));
 
     let target = 1000000000;
+    let cycle = [
+        Direction::Up,
+        Direction::Left,
+        Direction::Down,
+        Direction::Right,
+    ];
+    let spun = Spinner::new(&platform, &cycle).after(&platform, target);
+    log::debug!("after {} cycles:\n{}", target, spun);
+    println!(
+        "part 2 total load after {} cycles: {}",
+        target,
+        spun.load(Direction::Up)
+    );
 
-    let platform: Platform = input.parse().unwrap();
-    print_load_after(platform, target);
-}
-
-fn print_load_after(mut platform: Platform, target: usize) {
-    let mut cache = HashMap::new();
-    let mut indexed = Vec::new();
-    for i in 0..10000usize {
-        if let Some(existing) = cache.get(&platform) {
-            log::debug!("looped at i={} and {}", existing, i);
-            let j = (target - existing) % (i - existing);
-            let platform: &Platform = &indexed[j + existing];
-            log::debug!(
-                "after {} cycles:\n{}",
-                target,
-                platform.grid.display_with_rule(display_grid)
-            );
-            println!(
-                "part 2 total load after {} cycles: {}",
-                target,
-                platform.total_load()
-            );
-            return;
-        }
-        cache.insert(platform.clone(), i);
-        indexed.push(platform.clone());
-
-        for direction in [
-            Direction::Up,
-            Direction::Left,
-            Direction::Down,
-            Direction::Right,
-        ] {
-            platform.tilt(direction);
-        }
-
-        if i < 3 {
-            log::debug!(
-                "after {} cycles:\n{}",
-                i + 1,
-                platform.grid.display_with_rule(display_grid)
-            );
+    if context.args().is_empty() {
+        return;
+    }
+    let (mut spin, mut cycles, mut toward, mut export) =
+        (cycle.to_vec(), target, Direction::Up, None);
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "spin" => {
+                spin = value
+                    .chars()
+                    .map(|c| c.to_string().parse())
+                    .collect::<Result<_, _>>()
+                    .unwrap()
+            }
+            "cycles" => cycles = value.parse().expect("invalid number of cycles"),
+            "load" => toward = value.parse().unwrap(),
+            "export" => export = Some(value),
+            other => panic!("unknown argument '{}'", other),
         }
     }
-    panic!("max iterations exceeded");
-}
-
-fn display_grid(space: (&Point<usize>, &Option<Rock>)) -> char {
-    match space.1 {
-        None => '.',
-        Some(Rock::Round) => 'O',
-        Some(Rock::Square) => '#',
+    let spun = Spinner::new(&platform, &spin).after(&platform, cycles);
+    println!(
+        "load toward {} after {} cycles of {}: {}",
+        toward,
+        cycles,
+        spin.iter()
+            .map(|direction| direction.to_string())
+            .collect::<String>(),
+        spun.load(toward)
+    );
+    if let Some(path) = export {
+        std::fs::write(path, format!("{}\n", spun)).unwrap();
+        println!("platform written to {}", path);
     }
 }
 
-#[derive(Clone, Hash, Eq, PartialEq)]
+/// Cells are indexed row by row.
+#[derive(Clone)]
 struct Platform {
-    grid: Grid<Option<Rock>>,
+    len_x: usize,
+    len_y: usize,
+    square: Vec<bool>,
+    round: Vec<bool>,
 }
 
 impl Platform {
-    pub fn tilt(&mut self, direction: Direction) {
-        let ([outer, inner], to_point) = self.get_ranges(direction);
+    /// Every round rock counts its distance to the edge across from `toward`, plus 1.
+    pub fn load(&self, toward: Direction) -> u64 {
+        (0..self.round.len())
+            .filter(|&i| self.round[i])
+            .map(|i| {
+                let (x, y) = (i % self.len_x, i / self.len_x);
+                (match toward {
+                    Direction::Up => self.len_y - y,
+                    Direction::Down => y + 1,
+                    Direction::Left => self.len_x - x,
+                    Direction::Right => x + 1,
+                }) as u64
+            })
+            .sum()
+    }
+}
 
-        for outer in outer {
-            let mut empty = VecDeque::new();
-            for inner in inner.box_clone() {
-                let point = to_point(outer, inner);
-                match self.grid[&point] {
-                    None => {
-                        empty.push_back(point);
-                    }
-                    Some(Rock::Square) => {
-                        empty.clear();
-                    }
-                    Some(Rock::Round) => {
-                        if let Some(available) = empty.pop_front() {
-                            self.grid.swap(&point, &available);
-                            empty.push_back(point);
-                        }
-                    }
-                }
-            }
+/// Tilts a platform through a sequence of directions. Every direction has the runs of
+/// cells between square rocks, starting from the side the rocks roll toward, so a tilt
+/// only counts the round rocks in each run and stacks them at its start.
+struct Spinner {
+    tilts: Vec<Vec<Vec<usize>>>,
+}
+
+impl Spinner {
+    pub fn new(platform: &Platform, spin: &[Direction]) -> Self {
+        Self {
+            tilts: spin
+                .iter()
+                .map(|&direction| Self::runs(platform, direction))
+                .collect(),
         }
     }
-    pub fn total_load(&self) -> u64 {
-        self.grid
+    fn runs(platform: &Platform, direction: Direction) -> Vec<Vec<usize>> {
+        let (len_x, len_y) = (platform.len_x, platform.len_y);
+        let lines: Vec<Vec<usize>> = match direction {
+            Direction::Up => (0..len_x)
+                .map(|x| (0..len_y).map(|y| y * len_x + x).collect())
+                .collect(),
+            Direction::Down => (0..len_x)
+                .map(|x| (0..len_y).rev().map(|y| y * len_x + x).collect())
+                .collect(),
+            Direction::Left => (0..len_y)
+                .map(|y| (0..len_x).map(|x| y * len_x + x).collect())
+                .collect(),
+            Direction::Right => (0..len_y)
+                .map(|y| (0..len_x).rev().map(|x| y * len_x + x).collect())
+                .collect(),
+        };
+        lines
             .iter()
-            .filter(|(_, val)| *val == &Some(Rock::Round))
-            .map(|(point, _)| (self.grid.len_y() - point.y) as u64)
-            .sum()
+            .flat_map(|line| line.split(|&i| platform.square[i]))
+            .filter(|run| !run.is_empty())
+            .map(|run| run.to_vec())
+            .collect()
     }
-    fn get_ranges(&self, direction: Direction) -> ([Box<dyn RangeIterator>; 2], ToPointFun) {
-        match direction {
-            Direction::Up => (
-                [
-                    Box::new(0..self.grid.len_x()),
-                    Box::new(0..self.grid.len_y()),
-                ],
-                &|outer, inner| Point { x: outer, y: inner },
-            ),
-            Direction::Down => (
-                [
-                    Box::new(0..self.grid.len_x()),
-                    Box::new((0..self.grid.len_y()).rev()),
-                ],
-                &|outer, inner| Point { x: outer, y: inner },
-            ),
-            Direction::Left => (
-                [
-                    Box::new(0..self.grid.len_y()),
-                    Box::new(0..self.grid.len_x()),
-                ],
-                &|outer, inner| Point { x: inner, y: outer },
-            ),
-            Direction::Right => (
-                [
-                    Box::new(0..self.grid.len_y()),
-                    Box::new((0..self.grid.len_x()).rev()),
-                ],
-                &|outer, inner| Point { x: inner, y: outer },
-            ),
+    fn spin(&self, round: &mut [bool]) {
+        for runs in self.tilts.iter() {
+            for run in runs {
+                let count = run.iter().filter(|&&i| round[i]).count();
+                for (k, &i) in run.iter().enumerate() {
+                    round[i] = k < count;
+                }
+            }
         }
     }
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-enum Rock {
-    Round,
-    Square,
-}
-
-trait RangeIterator: Iterator<Item = usize> {
-    fn box_clone(&self) -> Box<dyn RangeIterator>;
-}
-impl<T> RangeIterator for T
-where
-    T: Iterator<Item = usize> + Clone + 'static,
-{
-    fn box_clone(&self) -> Box<dyn RangeIterator> {
-        Box::new(self.clone())
+    /// The platform after `cycles` full spins. The states eventually repeat,
+    /// from then on only the position in the loop matters.
+    pub fn after(&self, platform: &Platform, cycles: usize) -> Platform {
+        let mut seen = HashMap::new();
+        let mut states = vec![];
+        let mut round = platform.round.clone();
+        for i in 0..cycles {
+            if let Some(&start) = seen.get(&round) {
+                log::debug!("looped at i={} and {}", start, i);
+                let state: &Vec<bool> = &states[start + (cycles - start) % (i - start)];
+                round = state.clone();
+                break;
+            }
+            seen.insert(round.clone(), i);
+            states.push(round.clone());
+            self.spin(&mut round);
+        }
+        Platform {
+            round,
+            ..platform.clone()
+        }
     }
 }
 
-type ToPointFun = &'static dyn Fn(usize, usize) -> Point<usize>;
-
 impl FromStr for Platform {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let len_x = s.lines().next().map_or(0, |line| line.len());
+        let mut square = vec![];
+        let mut round = vec![];
+        for line in s.lines() {
+            if line.len() != len_x {
+                return Err(anyhow::anyhow!("all rows must be the same length"));
+            }
+            for char in line.chars() {
+                let (is_square, is_round) = match char {
+                    '.' => (false, false),
+                    'O' => (false, true),
+                    '#' => (true, false),
+                    other => return Err(anyhow::anyhow!("invalid character '{}'", other)),
+                };
+                square.push(is_square);
+                round.push(is_round);
+            }
+        }
         Ok(Self {
-            grid: Grid::try_from_iter(s.lines().map(|line| {
-                line.chars().map(|char| match char {
-                    '.' => Ok(None),
-                    'O' => Ok(Some(Rock::Round)),
-                    '#' => Ok(Some(Rock::Square)),
-                    other => Err(anyhow::anyhow!("invalid character '{}'", other)),
-                })
-            }))?,
+            len_x,
+            len_y: s.lines().count(),
+            square,
+            round,
         })
     }
 }
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.len_y {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for i in y * self.len_x..(y + 1) * self.len_x {
+                let c = match (self.square[i], self.round[i]) {
+                    (true, _) => '#',
+                    (_, true) => 'O',
+                    _ => '.',
+                };
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
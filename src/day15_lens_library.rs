@@ -1,185 +1,175 @@
-use crate::common::day_setup::Day;
-use std::collections::HashMap;
-use std::fmt::{Debug, Display, Formatter};
+use crate::common::bucket_map::BucketMap;
+use crate::common::day_setup::{AppContext, Day};
+use anyhow::Context as AnyhowContext;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7"])
-}
-pub fn run(input: &str) {
-    let sum: u64 = input
-        .split(",")
-        .map(|line| <&str as Into<AsciiString>>::into(line).get_hash() as u64)
-        .sum();
+    Day::custom(run).with_test_inputs(&["rn=1,cm-,qp=3,cm=2,qp-,pc=4,ot=9,ab=5,pc-,pc=6,ot=7"])
+}
+/// Takes `hash=<holiday|fnv>`, `buckets=<n>` and `aggregate=<focusing-power|focal-sum|lenses|fullest-box>`
+/// to run the steps through other boxes.
+pub fn run(context: &AppContext) {
+    let input = context.get_input();
+    let input = input.as_str();
+    let sum: u64 = input.split(",").map(holiday_hash).sum();
     println!("part 1 sum: {}", sum);
 
-    solve(input);
+    let steps = parse_steps(input).unwrap();
+    let boxes = arrange(&steps, 256, holiday_hash);
+    println!(
+        "part 2 focusing power sum: {}",
+        Aggregate::FocusingPower.apply(&boxes)
+    );
+
+    if context.args().is_empty() {
+        return;
+    }
+    let (mut hash, mut buckets, mut aggregate): (fn(&str) -> u64, _, _) =
+        (holiday_hash, 256, Aggregate::FocusingPower);
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "hash" => {
+                hash = match value {
+                    "holiday" => holiday_hash,
+                    "fnv" => fnv_hash,
+                    other => panic!("unknown hash '{}'", other),
+                }
+            }
+            "buckets" => buckets = value.parse().expect("invalid number of buckets"),
+            "aggregate" => aggregate = value.parse().unwrap(),
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+    let boxes = arrange(&steps, buckets, hash);
+    println!(
+        "{:?} with {} boxes: {}",
+        aggregate,
+        boxes.bucket_count(),
+        aggregate.apply(&boxes)
+    );
 }
 
-fn solve(input: &str) {
-    let mut boxes = LensBoxes::default();
-    for lens in input.split(",").map(|s| s.parse::<LensInput>().unwrap()) {
-        log::debug!("after \"{}\"", lens);
-        boxes.next(lens);
-        log::debug!("\n{}", boxes);
-        log::trace!("\n{:?}", boxes);
-    }
-    println!("part 2 focusing power sum: {}", boxes.get_focusing_power());
+/// The HASH algorithm from the puzzle.
+fn holiday_hash(s: &str) -> u64 {
+    s.bytes()
+        .fold(0u8, |value, byte| value.wrapping_add(byte).wrapping_mul(17)) as u64
 }
 
-struct LensBoxes {
-    boxes: [LensBox; 256],
+/// 64-bit FNV-1a, for a spread over more than 256 boxes.
+fn fnv_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |value, byte| {
+        (value ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
-impl Default for LensBoxes {
-    fn default() -> Self {
-        Self {
-            boxes: core::array::from_fn(|_| Default::default()),
-        }
-    }
+/// Malformed steps are reported with their 1-based position.
+fn parse_steps(input: &str) -> anyhow::Result<Vec<Step>> {
+    input
+        .split(",")
+        .enumerate()
+        .map(|(i, step)| {
+            step.parse()
+                .with_context(|| format!("step {} '{}' is malformed", i + 1, step))
+        })
+        .collect()
 }
 
-impl LensBoxes {
-    pub fn next(&mut self, lens: LensInput) {
-        let box_id = lens.label.get_hash();
-        let box_ = &mut self.boxes[box_id as usize];
-        if let Operation::Remove = &lens.operation {
-            box_.remove(&lens.label);
-        } else {
-            box_.add(lens.try_into().unwrap());
+/// Label to focal length, in boxes picked by `hash`.
+fn arrange(steps: &[Step], buckets: usize, hash: fn(&str) -> u64) -> BucketMap<String, u8> {
+    let mut boxes = BucketMap::new(buckets, move |label: &String| hash(label));
+    for step in steps {
+        match step.operation {
+            Operation::Remove => {
+                boxes.remove(&step.label);
+            }
+            Operation::Add(focal_length) => {
+                boxes.insert(step.label.clone(), focal_length);
+            }
         }
+        log::debug!("after \"{}\"", step);
+        log::debug!("\n{}", Boxes(&boxes));
     }
-    pub fn get_focusing_power(&self) -> u64 {
-        self.boxes
-            .iter()
-            .enumerate()
-            .map(|(i, box_)| box_.get_focusing_power(i))
-            .sum()
-    }
+    boxes
 }
 
-#[derive(Default, Debug)]
-struct LensBox {
-    lenses: Vec<Lens>,
-    indices: HashMap<AsciiString, usize>,
+#[derive(Debug, Copy, Clone)]
+enum Aggregate {
+    /// box number times slot number times focal length
+    FocusingPower,
+    FocalSum,
+    Lenses,
+    /// the most lenses in any box
+    FullestBox,
 }
 
-impl LensBox {
-    pub fn remove(&mut self, label: &AsciiString) -> Option<Lens> {
-        if let Some(&index) = self.indices.get(label) {
-            let lens = self.lenses.remove(index);
-            for other in &self.lenses[index..] {
-                *self.indices.get_mut(&other.label).unwrap() -= 1;
-            }
-            self.indices.remove(label);
-            Some(lens)
-        } else {
-            None
+impl Aggregate {
+    pub fn apply(&self, boxes: &BucketMap<String, u8>) -> u64 {
+        match self {
+            Aggregate::FocusingPower => boxes.weighted_sum(|&focal_length| focal_length as u64),
+            Aggregate::FocalSum => boxes.sum(|&focal_length| focal_length as u64),
+            Aggregate::Lenses => boxes.len() as u64,
+            Aggregate::FullestBox => boxes.max_bucket_len() as u64,
         }
     }
-    pub fn add(&mut self, mut lens: Lens) -> Option<Lens> {
-        if let Some(&index) = self.indices.get(&lens.label) {
-            std::mem::swap(&mut lens, &mut self.lenses[index]);
-            Some(lens)
-        } else {
-            let index = self.lenses.len();
-            self.indices.insert(lens.label.clone(), index);
-            self.lenses.push(lens);
-
-            None
-        }
-    }
-    pub fn get_focusing_power(&self, box_number: usize) -> u64 {
-        self.lenses
-            .iter()
-            .enumerate()
-            .map(|(i, lens)| lens.get_focusing_power(box_number, i))
-            .sum()
-    }
 }
 
-struct Lens {
-    label: AsciiString,
-    focal_length: u8,
-}
+impl FromStr for Aggregate {
+    type Err = anyhow::Error;
 
-impl Lens {
-    pub fn get_focusing_power(&self, box_number: usize, lens_number: usize) -> u64 {
-        (1 + box_number as u64) * (1 + lens_number as u64) * self.focal_length as u64
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "focusing-power" => Self::FocusingPower,
+            "focal-sum" => Self::FocalSum,
+            "lenses" => Self::Lenses,
+            "fullest-box" => Self::FullestBox,
+            other => return Err(anyhow::anyhow!("unknown aggregate '{}'", other)),
+        })
     }
 }
 
-struct LensInput {
-    label: AsciiString,
+struct Step {
+    label: String,
     operation: Operation,
 }
 
-impl TryFrom<LensInput> for Lens {
-    type Error = anyhow::Error;
-
-    fn try_from(value: LensInput) -> Result<Self, Self::Error> {
-        match value.operation {
-            Operation::Remove => Err(anyhow::anyhow!("cannot convert {} to a lens", value)),
-            Operation::Add(focal_length) => Ok(Self {
-                label: value.label,
-                focal_length,
-            }),
-        }
-    }
-}
-
 enum Operation {
     Remove,
     Add(u8),
 }
 
-#[derive(Default, Eq, PartialEq, Hash, Clone)]
-struct AsciiString(Vec<char>);
-
-impl AsciiString {
-    pub fn get_hash(&self) -> u8 {
-        let mut value = 0u8;
-        for &c in self.0.iter() {
-            value = value.wrapping_add(c as u8);
-            value = value.wrapping_mul(17);
-        }
-        value
-    }
-}
-
-impl<'a> From<&'a str> for AsciiString {
-    fn from(value: &'a str) -> Self {
-        Self(value.chars().collect())
-    }
-}
-
-impl FromStr for LensInput {
+impl FromStr for Step {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.ends_with('-') {
+        if let Some(label) = s.strip_suffix('-') {
             return Ok(Self {
-                label: AsciiString(s.chars().take_while(|c| c.is_alphabetic()).collect()),
+                label: label.to_string(),
                 operation: Operation::Remove,
             });
         }
         let [label, op_val] = s.split("=").collect::<Vec<_>>()[..] else {
-            return Err(anyhow::anyhow!("invalid lens string '{}'", s));
+            return Err(anyhow::anyhow!(
+                "expected '<label>=<focal length>' or '<label>-'"
+            ));
         };
-        let op_val: u8 = op_val.parse()?;
+        let op_val: u8 = op_val
+            .parse()
+            .with_context(|| format!("invalid focal length '{}'", op_val))?;
         if !(1..=9).contains(&op_val) {
             return Err(anyhow::anyhow!("invalid op val '{}'", op_val));
         }
         Ok(Self {
-            label: label.into(),
+            label: label.to_string(),
             operation: Operation::Add(op_val),
         })
     }
 }
 
-impl Display for LensInput {
+impl Display for Step {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.label.0.iter().collect::<String>())?;
+        write!(f, "{}", self.label)?;
         match &self.operation {
             Operation::Remove => {
                 write!(f, "-")
@@ -191,61 +181,21 @@ impl Display for LensInput {
     }
 }
 
-impl Display for LensBox {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for lens in &self.lenses {
-            write!(f, "  {}", lens)?;
-        }
-        Ok(())
-    }
-}
+/// Only the boxes holding any lenses.
+struct Boxes<'a>(&'a BucketMap<String, u8>);
 
-impl Display for Lens {
+impl Display for Boxes<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "[{} {}]",
-            self.label.0.iter().collect::<String>(),
-            self.focal_length
-        )
-    }
-}
-
-impl Display for LensBoxes {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for (i, box_) in self
-            .boxes
-            .iter()
-            .enumerate()
-            .filter(|(_, b)| !b.lenses.is_empty())
-        {
-            writeln!(f, "Box {}:{}", i, box_)?;
-        }
-        Ok(())
-    }
-}
-
-impl Debug for Lens {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
-    }
-}
-
-impl Debug for AsciiString {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0.iter().collect::<String>())
-    }
-}
-
-impl Debug for LensBoxes {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for (i, box_) in self
-            .boxes
-            .iter()
-            .enumerate()
-            .filter(|(_, b)| !b.lenses.is_empty())
-        {
-            writeln!(f, "Box {}:{:?} | {:?}", i, box_.lenses, box_.indices)?;
+        let mut current = None;
+        for entry in self.0.iter() {
+            if current != Some(entry.bucket) {
+                if current.is_some() {
+                    writeln!(f)?;
+                }
+                write!(f, "Box {}:", entry.bucket)?;
+                current = Some(entry.bucket);
+            }
+            write!(f, "  [{} {}]", entry.key, entry.value)?;
         }
         Ok(())
     }
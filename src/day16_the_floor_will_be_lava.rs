@@ -1,11 +1,14 @@
-use crate::common::day_setup::Day;
+use crate::common::day_setup::{AppContext, Day};
+use crate::common::graph::strongly_connected_components;
 use crate::common::models::grid::GridLike;
 use crate::common::models::{Direction, Grid, Point};
 use colored::Colorize;
-use std::collections::{HashMap, HashSet};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
 use std::str::FromStr;
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&[r".|...\....
+    Day::custom(run).with_test_inputs(&[r".|...\....
 |.-.\.....
 .....|-...
 ........|.
@@ -17,59 +20,229 @@ pub fn day() -> Day {
 ..//.|...."])
 }
 
-pub fn run(input: &str) {
-    let contraption: Contraption = input.parse().unwrap();
+/// `place=<k>` looks for the best spots for up to `k` more mirrors or splitters, for a beam
+/// entering at `entry=<x>,<y>,<direction>` (the top left going right by default).
+/// `width=<n>` only follows the `n` best placements at each step, for a faster but inexact search.
+pub fn run(context: &AppContext) {
+    let contraption: Contraption = context.get_input().parse().unwrap();
+    let mut graph = BeamGraph::new(&contraption);
+    let energized = graph.energized_from(&BeamPoint::new(Point::default(), Direction::Right));
+    log::debug!(
+        "energized:\n{}",
+        contraption.grid.display_with_rule(|(point, _)| {
+            if energized.contains(graph.tile(point)) {
+                "#".blue()
+            } else {
+                ".".white()
+            }
+        })
+    );
+    println!("part 1 energized count: {}", energized.len());
+
+    let max = contraption
+        .edge_entries()
+        .map(|start| graph.energized_from(&start).len())
+        .max()
+        .unwrap();
+    println!("part 2 max energized count: {}", max);
+
+    if context.args().is_empty() {
+        return;
+    }
+    let mut search = PlacementSearch {
+        contraption: &contraption,
+        start: BeamPoint::new(Point::default(), Direction::Right),
+        max_pieces: 1,
+        width: None,
+        best: (0, vec![]),
+    };
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "place" => search.max_pieces = value.parse().expect("invalid number of pieces"),
+            "width" => search.width = Some(value.parse().expect("invalid width")),
+            "entry" => {
+                let [x, y, direction] = value.split(',').collect::<Vec<_>>()[..] else {
+                    panic!("expected entry=<x>,<y>,<direction>");
+                };
+                search.start = contraption
+                    .entry(
+                        Point {
+                            x: x.parse().expect("invalid x"),
+                            y: y.parse().expect("invalid y"),
+                        },
+                        direction.parse().expect("invalid direction"),
+                    )
+                    .unwrap();
+            }
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+    let (score, placed) = search.run();
+    let entering = contraption.simulate(&search.start, &placed);
     println!(
-        "part 1 energized count: {}",
-        contraption.energized_count_from(BeamPoint::new(Point::default(), Direction::Right))
+        "best layout with up to {} more pieces energizes {} tiles:{}",
+        search.max_pieces,
+        score,
+        contraption.grid.display_overriding(|point| {
+            let tile = point.y * contraption.grid.len_x() + point.x;
+            let space = contraption.space_at(point, &placed);
+            Some(if placed.iter().any(|(placed, _)| placed == point) {
+                space.to_string().red().bold()
+            } else if entering[tile] != 0 {
+                space.to_string().blue()
+            } else {
+                space.to_string().normal()
+            })
+        })
     );
+}
 
-    optimize_energized(&contraption);
+/// A mirror or splitter added on an empty tile.
+type Placement = (Point<usize>, Space);
+
+/// Depth first over the pieces to add. Only empty tiles the beam goes through can change
+/// anything, and only with pieces that turn or split some beam going through them.
+/// Each layout is built in one order only: once a placement has been tried at some step,
+/// the later branches of that step leave it out. Branches that cannot beat the best layout
+/// so far, even if every remaining piece went where it helps most, are cut.
+struct PlacementSearch<'a> {
+    contraption: &'a Contraption,
+    start: BeamPoint,
+    max_pieces: usize,
+    /// how many of the best next placements to follow, all of them if `None`
+    width: Option<usize>,
+    best: (usize, Vec<Placement>),
 }
 
-fn optimize_energized(contraption: &Contraption) {
-    let max: usize = [
-        (
-            Direction::Right,
-            (0..contraption.grid.len_y())
-                .map(|y| Point { x: 0, y })
-                .collect::<Vec<_>>(),
-        ),
-        (
-            Direction::Down,
-            (0..contraption.grid.len_x())
-                .map(|x| Point { x, y: 0 })
-                .collect(),
-        ),
-        (
-            Direction::Left,
-            (0..contraption.grid.len_y())
-                .map(|y| Point {
-                    x: contraption.grid.len_x() - 1,
-                    y,
-                })
-                .collect(),
-        ),
-        (
-            Direction::Up,
-            (0..contraption.grid.len_x())
-                .map(|x| Point {
-                    x,
-                    y: contraption.grid.len_y() - 1,
+impl PlacementSearch<'_> {
+    pub fn run(&mut self) -> (usize, Vec<Placement>) {
+        let entering = self.contraption.simulate(&self.start, &[]);
+        self.best = (Contraption::energized_count(&entering), vec![]);
+        self.search(&mut vec![], &mut vec![], &entering);
+        self.best.clone()
+    }
+    fn search(&mut self, placed: &mut Vec<Placement>, tried: &mut Vec<Placement>, entering: &[u8]) {
+        let tile_count = entering.len();
+        if placed.len() == self.max_pieces
+            || self.best.0 == tile_count
+            || self.upper_bound(placed) <= self.best.0
+        {
+            return;
+        }
+        let len_x = self.contraption.grid.len_x();
+        let candidates: Vec<Placement> = entering
+            .iter()
+            .enumerate()
+            .filter(|&(_, &directions)| directions != 0)
+            .map(|(tile, &directions)| {
+                let point = Point {
+                    x: tile % len_x,
+                    y: tile / len_x,
+                };
+                (point, directions)
+            })
+            .filter(|(point, _)| self.contraption.space_at(point, placed) == Space::Empty)
+            .flat_map(|(point, directions)| {
+                Space::pieces().iter().copied().filter_map(move |piece| {
+                    let turns = Direction::all()
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, _)| directions & (1 << i) != 0)
+                        .any(|(_, &direction)| piece.redirect(direction) != (direction, None));
+                    turns.then_some((point, piece))
                 })
-                .collect(),
-        ),
-    ]
-    .into_iter()
-    .flat_map(|(direction, points)| {
-        points
-            .into_iter()
-            .map(move |start| BeamPoint::new(start, direction))
-    })
-    .map(|start| contraption.energized_count_from(start))
-    .max()
-    .unwrap();
-    println!("part 2 max energized count: {}", max);
+            })
+            .filter(|placement| !tried.contains(placement))
+            .collect();
+        let mut evaluated: Vec<_> = candidates
+            .par_iter()
+            .map(|&placement| {
+                let mut layout = placed.clone();
+                layout.push(placement);
+                let entering = self.contraption.simulate(&self.start, &layout);
+                (Contraption::energized_count(&entering), placement, entering)
+            })
+            .collect();
+        evaluated.sort_unstable_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+        evaluated.truncate(self.width.unwrap_or(usize::MAX));
+
+        let tried_len = tried.len();
+        for (score, placement, entering) in evaluated {
+            placed.push(placement);
+            if score > self.best.0 {
+                log::debug!("{} energized tiles with {:?}", score, placed);
+                self.best = (score, placed.clone());
+            }
+            self.search(placed, tried, &entering);
+            placed.pop();
+            tried.push(placement);
+        }
+        tried.truncate(tried_len);
+    }
+    /// The most tiles any layout with `placed` and the remaining pieces can energize: the tiles
+    /// a beam can reach turning at no more empty tiles than there are pieces left. A placed
+    /// piece never sends a beam back, and a splitter lets beams through along it, so every
+    /// energized tile is reached along some such path.
+    fn upper_bound(&self, placed: &[Placement]) -> usize {
+        let grid = &self.contraption.grid;
+        let len_x = grid.len_x();
+        let remaining = self.max_pieces - placed.len();
+        let state = |beam: &BeamPoint| {
+            let direction = Direction::all()
+                .iter()
+                .position(|&direction| direction == beam.direction)
+                .unwrap();
+            (beam.location.y * len_x + beam.location.x) * 4 + direction
+        };
+        // 0-1 BFS: following the layout is free, turning on an empty tile uses a piece
+        let mut pieces_used = vec![usize::MAX; len_x * grid.len_y() * 4];
+        let mut queue = VecDeque::from([(self.start.clone(), 0)]);
+        pieces_used[state(&self.start)] = 0;
+        while let Some((beam, used)) = queue.pop_front() {
+            if used > pieces_used[state(&beam)] {
+                continue;
+            }
+            let space = self.contraption.space_at(&beam.location, placed);
+            let exits: Vec<(Direction, usize)> = if space == Space::Empty {
+                vec![
+                    (beam.direction, 0),
+                    (beam.direction.turn_left(), 1),
+                    (beam.direction.turn_right(), 1),
+                ]
+            } else {
+                let (first, second) = space.redirect(beam.direction);
+                [Some(first), second]
+                    .into_iter()
+                    .flatten()
+                    .map(|direction| (direction, 0))
+                    .collect()
+            };
+            for (direction, cost) in exits {
+                let used = used + cost;
+                if used > remaining {
+                    continue;
+                }
+                let Some(next) = grid.move_in_direction_if(&beam.location, direction, |_| true)
+                else {
+                    continue;
+                };
+                let next = BeamPoint::new(next, direction);
+                if used < pieces_used[state(&next)] {
+                    pieces_used[state(&next)] = used;
+                    if cost == 0 {
+                        queue.push_front((next, used));
+                    } else {
+                        queue.push_back((next, used));
+                    }
+                }
+            }
+        }
+        pieces_used
+            .chunks(4)
+            .filter(|tile| tile.iter().any(|&used| used <= remaining))
+            .count()
+    }
 }
 
 struct Contraption {
@@ -77,57 +250,74 @@ struct Contraption {
 }
 
 impl Contraption {
-    pub fn energized_count_from(&self, start: BeamPoint) -> usize {
-        let mut cache = SimpleCache::default();
-        self.fill_out_energized_from(start.clone(), &mut cache);
-        log::debug!(
-            "energized:\n{}",
-            self.grid.display_with_rule(|(point, _)| {
-                if cache.cache.contains(point) {
-                    "#".blue()
-                } else {
-                    ".".white()
-                }
-            })
-        );
-        cache.cache.len()
+    /// Beams coming in from outside, on every edge cell.
+    pub fn edge_entries(&self) -> impl Iterator<Item = BeamPoint> + '_ {
+        let (len_x, len_y) = (self.grid.len_x(), self.grid.len_y());
+        (0..len_y)
+            .map(|y| BeamPoint::new(Point { x: 0, y }, Direction::Right))
+            .chain((0..len_x).map(|x| BeamPoint::new(Point { x, y: 0 }, Direction::Down)))
+            .chain(
+                (0..len_y).map(move |y| BeamPoint::new(Point { x: len_x - 1, y }, Direction::Left)),
+            )
+            .chain(
+                (0..len_x).map(move |x| BeamPoint::new(Point { x, y: len_y - 1 }, Direction::Up)),
+            )
     }
-    fn fill_out_energized_from(&self, start: BeamPoint, cache: &mut SimpleCache) {
-        let mut beams = vec![BeamPath::new(vec![start])];
-        while !beams.is_empty() {
-            beams = beams
-                .into_iter()
-                .filter_map(|beam| {
-                    let point = beam.points.iter().last().unwrap();
-                    if cache.visited(point) {
-                        return None;
-                    }
-                    cache.visit(point.clone());
-                    if beam.is_loop().is_some() {
-                        return None;
-                    }
-                    let next: Vec<_> = self.enter(point).collect();
-                    match next.len() {
-                        0 => None,
-                        1 => {
-                            let mut path = beam;
-                            path.push(next.into_iter().next().unwrap());
-                            Some(vec![path])
-                        }
-                        _ => Some(
-                            next.into_iter()
-                                .map(move |next| {
-                                    let mut path = beam.clone();
-                                    path.push(next);
-                                    path
-                                })
-                                .collect(),
-                        ),
-                    }
-                })
-                .flatten()
-                .collect();
+    /// A beam entering at `location`, which has to be on the grid.
+    pub fn entry(&self, location: Point<usize>, direction: Direction) -> anyhow::Result<BeamPoint> {
+        if location.x >= self.grid.len_x() || location.y >= self.grid.len_y() {
+            return Err(anyhow::anyhow!(
+                "entry {} is outside the {}x{} grid",
+                location,
+                self.grid.len_x(),
+                self.grid.len_y()
+            ));
+        }
+        Ok(BeamPoint::new(location, direction))
+    }
+    fn space_at(&self, point: &Point<usize>, placed: &[Placement]) -> Space {
+        placed
+            .iter()
+            .find(|(placed, _)| placed == point)
+            .map_or(self.grid[point], |&(_, space)| space)
+    }
+    /// For every tile, the directions beams enter it in as bits (in [Direction::all] order),
+    /// with the `placed` pieces on top of the grid.
+    fn simulate(&self, start: &BeamPoint, placed: &[Placement]) -> Vec<u8> {
+        let len_x = self.grid.len_x();
+        let mut entering = vec![0u8; len_x * self.grid.len_y()];
+        let bit = |direction: Direction| {
+            1 << Direction::all()
+                .iter()
+                .position(|&other| other == direction)
+                .unwrap()
+        };
+        let mut beams = vec![start.clone()];
+        while let Some(beam) = beams.pop() {
+            let tile = beam.location.y * len_x + beam.location.x;
+            if entering[tile] & bit(beam.direction) != 0 {
+                continue;
+            }
+            entering[tile] |= bit(beam.direction);
+            let (first, second) = self
+                .space_at(&beam.location, placed)
+                .redirect(beam.direction);
+            for direction in [Some(first), second].into_iter().flatten() {
+                if let Some(next) =
+                    self.grid
+                        .move_in_direction_if(&beam.location, direction, |_| true)
+                {
+                    beams.push(BeamPoint::new(next, direction));
+                }
+            }
         }
+        entering
+    }
+    fn energized_count(entering: &[u8]) -> usize {
+        entering
+            .iter()
+            .filter(|&&directions| directions != 0)
+            .count()
     }
     fn enter(&self, beam: &BeamPoint) -> impl Iterator<Item = BeamPoint> {
         let (first, second) = self.grid[&beam.location].redirect(beam.direction);
@@ -146,49 +336,148 @@ impl Contraption {
     }
 }
 
-#[derive(Default)]
-struct SimpleCache {
-    cache: HashSet<Point<usize>>,
-    visited: HashSet<BeamPoint>,
+/// Every beam state (a tile and the direction the beam enters it in) and where it goes next,
+/// condensed into its strongly connected components: all the states of a component energize
+/// the same tiles. Components reachable along more than one path keep their energized tiles,
+/// so entries that run into the same beams share the work.
+struct BeamGraph {
+    len_x: usize,
+    /// tiles energized by the states of each component
+    tiles: Vec<Vec<usize>>,
+    /// component of each state
+    components: Vec<usize>,
+    /// the components each one leads to
+    next: Vec<Vec<usize>>,
+    shared: Vec<bool>,
+    memo: HashMap<usize, Rc<TileSet>>,
 }
 
-impl SimpleCache {
-    pub fn visited(&self, point: &BeamPoint) -> bool {
-        self.visited.contains(point)
+impl BeamGraph {
+    pub fn new(contraption: &Contraption) -> Self {
+        let len_x = contraption.grid.len_x();
+        let state_count = len_x * contraption.grid.len_y() * 4;
+        let adjacency: Vec<Vec<usize>> = (0..state_count)
+            .map(|state| {
+                contraption
+                    .enter(&Self::beam_point(len_x, state))
+                    .map(|next| Self::state(len_x, &next))
+                    .collect()
+            })
+            .collect();
+        let components = strongly_connected_components(&adjacency);
+        let component_count = components.iter().max().map_or(0, |&max| max + 1);
+
+        let mut tiles = vec![vec![]; component_count];
+        let mut next = vec![vec![]; component_count];
+        let mut in_degree = vec![0; component_count];
+        for (state, edges) in adjacency.iter().enumerate() {
+            let component = components[state];
+            tiles[component].push(state / 4);
+            for &to in edges {
+                if components[to] != component && !next[component].contains(&components[to]) {
+                    next[component].push(components[to]);
+                    in_degree[components[to]] += 1;
+                }
+            }
+        }
+        for tiles in tiles.iter_mut() {
+            tiles.sort_unstable();
+            tiles.dedup();
+        }
+        log::debug!(
+            "{} beam states in {} components",
+            state_count,
+            component_count
+        );
+
+        Self {
+            len_x,
+            tiles,
+            components,
+            next,
+            shared: in_degree.into_iter().map(|degree| degree > 1).collect(),
+            memo: HashMap::new(),
+        }
     }
-    pub fn visit(&mut self, point: BeamPoint) {
-        self.cache.insert(point.location);
-        self.visited.insert(point);
+    fn state(len_x: usize, beam: &BeamPoint) -> usize {
+        let direction = Direction::all()
+            .iter()
+            .position(|&direction| direction == beam.direction)
+            .unwrap();
+        (beam.location.y * len_x + beam.location.x) * 4 + direction
+    }
+    fn beam_point(len_x: usize, state: usize) -> BeamPoint {
+        let tile = state / 4;
+        BeamPoint::new(
+            Point {
+                x: tile % len_x,
+                y: tile / len_x,
+            },
+            Direction::all()[state % 4],
+        )
+    }
+    pub fn tile(&self, point: &Point<usize>) -> usize {
+        point.y * self.len_x + point.x
+    }
+    /// The tiles energized by a beam entering `start`.
+    pub fn energized_from(&mut self, start: &BeamPoint) -> Rc<TileSet> {
+        let root = self.components[Self::state(self.len_x, start)];
+        self.shared[root] = true;
+        let tile_count = self.components.len() / 4;
+        // post-order over the components below `root`: a component's tiles plus everything
+        // after it. Components that are not shared have a single parent that takes them.
+        let mut pending = HashMap::new();
+        let mut stack = vec![(root, false)];
+        while let Some((component, expanded)) = stack.pop() {
+            if self.memo.contains_key(&component) || pending.contains_key(&component) {
+                continue;
+            }
+            if !expanded {
+                stack.push((component, true));
+                stack.extend(self.next[component].iter().map(|&next| (next, false)));
+                continue;
+            }
+            let mut energized = TileSet::new(tile_count);
+            for &tile in self.tiles[component].iter() {
+                energized.insert(tile);
+            }
+            for next in self.next[component].iter() {
+                match self.memo.get(next) {
+                    Some(memo) => energized.union_with(memo),
+                    None => energized.union_with(&pending.remove(next).unwrap()),
+                }
+            }
+            if self.shared[component] {
+                self.memo.insert(component, Rc::new(energized));
+            } else {
+                pending.insert(component, energized);
+            }
+        }
+        self.memo[&root].clone()
     }
-}
-#[derive(Default, Clone, Debug)]
-struct BeamPath {
-    points: Vec<BeamPoint>,
-    indexed: HashMap<BeamPoint, u8>,
 }
 
-impl BeamPath {
-    pub fn new(points: Vec<BeamPoint>) -> Self {
-        let indexed = points.iter().map(|point| (point.clone(), 1)).collect();
-        Self { points, indexed }
+/// A set of tile indices.
+#[derive(Clone)]
+struct TileSet(Vec<u64>);
+
+impl TileSet {
+    pub fn new(tile_count: usize) -> Self {
+        Self(vec![0; tile_count.div_ceil(64)])
     }
-    pub fn is_loop(&self) -> Option<(&[BeamPoint], &[BeamPoint])> {
-        let value = self.points.last().unwrap();
-        if self.indexed.get(value).unwrap() > &1 {
-            let end_non_inclusive = self.points.len() - 1;
-            let start = self.points.iter().position(|point| point == value).unwrap();
-            if start == end_non_inclusive {
-                panic!("invalid loop...");
-            }
-            let the_loop = &self.points[start..end_non_inclusive];
-            let before_loop = &self.points[0..start];
-            return Some((the_loop, before_loop));
+    pub fn insert(&mut self, tile: usize) {
+        self.0[tile / 64] |= 1 << (tile % 64);
+    }
+    pub fn contains(&self, tile: usize) -> bool {
+        self.0[tile / 64] & (1 << (tile % 64)) != 0
+    }
+    pub fn union_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
         }
-        None
     }
-    pub fn push(&mut self, next: BeamPoint) {
-        *self.indexed.entry(next.clone()).or_default() += 1;
-        self.points.push(next);
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
     }
 }
 
@@ -207,7 +496,7 @@ impl BeamPoint {
     }
 }
 
-#[derive(strum_macros::Display, Copy, Clone, Eq, PartialEq)]
+#[derive(strum_macros::Display, Copy, Clone, Eq, PartialEq, Hash, Debug)]
 enum Space {
     #[strum(serialize = ".")]
     Empty,
@@ -222,6 +511,15 @@ enum Space {
 }
 
 impl Space {
+    /// Everything that can go on an empty tile.
+    pub fn pieces() -> &'static [Space] {
+        &[
+            Space::MirrorFront,
+            Space::MirrorBack,
+            Space::SplitterHorizontal,
+            Space::SplitterVertical,
+        ]
+    }
     pub fn redirect(&self, incoming: Direction) -> (Direction, Option<Direction>) {
         match self {
             Space::Empty => (incoming, None),
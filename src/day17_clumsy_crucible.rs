@@ -1,14 +1,14 @@
-use crate::common::day_setup::Day;
+use crate::common::bucket_queue::BucketQueue;
+use crate::common::day_setup::{AppContext, Day};
 use crate::common::models::grid::GridLike;
 use crate::common::models::{Direction, Grid, Point};
 use anyhow::Context as AnyhowContext;
 use colored::Colorize;
 use std::collections::HashMap;
 use std::str::FromStr;
-use utils::a_star;
-use utils::a_star::{NodeSuccessorConverter, Successor};
+
 pub fn day() -> Day {
-    Day::new(run).with_test_inputs(&["2413432311323
+    Day::custom(run).with_test_inputs(&["2413432311323
 3215453535623
 3255245654254
 3446585845452
@@ -23,176 +23,387 @@ pub fn day() -> Day {
 4322674655533"])
 }
 
-pub fn run(input: &str) {
-    let map: CityMap = input.parse().unwrap();
-
-    let cost = map.min_heat_loss(Crucible::new(Point::default(), Direction::Right));
-    println!("part 1 min heat loss: {}", cost);
+/// Takes `from=<x>,<y>`, `to=<x>,<y>`, `runs=<min>..<max>` and `reverse=true` to query other
+/// trips and movers, `paths=<k>` for the `k` cheapest paths and `heatmap=true` for the lowest
+/// heat loss to every block.
+pub fn run(context: &AppContext) {
+    let map: CityMap = context.get_input().parse().unwrap();
+    let (start, end) = (Point::default(), map.bottom_right());
 
-    let cost = map.min_heat_loss(UltraCrucible(Crucible::new(
-        Point::default(),
-        Direction::Right,
-    )));
-    println!("part 2 min heat loss: {}", cost);
-}
+    let path = map
+        .min_heat_loss(&Mover::crucible(), start, end)
+        .expect("no path for the crucible");
+    println!("shortest path:{}", map.render_path(&path));
+    println!("part 1 min heat loss: {}", path.heat_loss);
 
-struct CityMap {
-    grid: Grid<u8>,
-}
+    let path = map
+        .min_heat_loss(&Mover::ultra_crucible(), start, end)
+        .expect("no path for the ultra crucible");
+    println!("shortest path:{}", map.render_path(&path));
+    println!("part 2 min heat loss: {}", path.heat_loss);
 
-impl CityMap {
-    pub fn min_heat_loss(&self, start: impl CrucibleLike) -> u64 {
-        let end = Point {
-            x: self.grid.len_x() - 1,
-            y: self.grid.len_y() - 1,
-        };
-        let result = a_star::a_star_search(
+    if context.args().is_empty() {
+        return;
+    }
+    let (mut start, mut end, mut mover) = (start, end, Mover::crucible());
+    let (mut paths, mut heatmap) = (1, false);
+    let parse_point = |value: &str| {
+        let (x, y) = value.split_once(',').expect("expected <x>,<y>");
+        map.block(Point {
+            x: x.parse().expect("invalid x"),
+            y: y.parse().expect("invalid y"),
+        })
+        .unwrap()
+    };
+    for arg in context.args() {
+        let (name, value) = arg.split_once('=').expect("expected <name>=<value>");
+        match name {
+            "from" => start = parse_point(value),
+            "to" => end = parse_point(value),
+            "runs" => {
+                let (min, max) = value.split_once("..").expect("expected <min>..<max>");
+                mover = mover
+                    .with_runs(
+                        min.parse().expect("invalid min run"),
+                        max.parse().expect("invalid max run"),
+                    )
+                    .unwrap();
+            }
+            "reverse" => mover.can_reverse = value.parse().expect("expected reverse=<true|false>"),
+            "paths" => paths = value.parse().expect("invalid number of paths"),
+            "heatmap" => heatmap = value.parse().expect("expected heatmap=<true|false>"),
+            other => panic!("unknown argument '{}'", other),
+        }
+    }
+    for (i, path) in map
+        .cheapest_paths(&mover, start, end, paths)
+        .iter()
+        .enumerate()
+    {
+        println!(
+            "path {} from {} to {} loses {} heat:{}",
+            i + 1,
             start,
-            |current| current.next(&self.grid),
-            |details| details.current_node.position().manhattan_distance(&end) as u64,
-            |current| current.position() == end,
-            None,
-        )
-        .unwrap();
-        let paths: HashMap<Point<usize>, Direction> = result
-            .shortest_path
-            .iter()
-            .skip(1)
-            .map(|point| (point.position(), point.direction()))
-            .collect();
+            end,
+            path.heat_loss,
+            map.render_path(path)
+        );
+    }
+    if heatmap {
         println!(
-            "shortest path:{}",
-            self.grid.display_overriding(|point| paths
-                .get(point)
-                .map(|val| val.to_string().bright_blue()))
+            "heat loss from {}:{}",
+            start,
+            render_heatmap(&map.heatmap(&mover, start))
         );
-        result.shortest_path_cost
     }
 }
 
-trait CrucibleLike: a_star::Node + Sized {
-    fn next(&self, grid: &Grid<u8>) -> Vec<Successor<Self, u64>>;
-    fn position(&self) -> Point<usize>;
-    fn direction(&self) -> Direction;
+/// Green for no heat loss up to red for the most, `·` where the crucible cannot stop.
+fn render_heatmap(heatmap: &Grid<Option<u64>>) -> String {
+    let max = heatmap
+        .iter()
+        .filter_map(|(_, &value)| value)
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    heatmap
+        .display_with_rule(|(_, value)| match value {
+            Some(value) => {
+                let shade = (value * 255 / max) as u8;
+                "█".truecolor(shade, 255 - shade, 0)
+            }
+            None => "·".normal(),
+        })
+        .to_string()
 }
 
-#[derive(Eq, PartialEq, Hash, Debug)]
-struct Crucible {
-    position: Point<usize>,
-    direction: Direction,
-    forward_times: u8,
+/// How a crucible moves: at least `min_run` and at most `max_run` blocks in a straight line
+/// before it turns or stops, maybe also turning back. `cost` is the heat lost entering a block.
+#[derive(Debug, Clone)]
+struct Mover {
+    min_run: u8,
+    max_run: u8,
+    can_reverse: bool,
+    cost: fn(u8) -> u64,
 }
 
-impl Crucible {
-    pub fn new(position: Point<usize>, direction: Direction) -> Self {
+impl Mover {
+    pub fn crucible() -> Self {
         Self {
-            position,
-            direction,
-            forward_times: 0,
+            min_run: 1,
+            max_run: 3,
+            can_reverse: false,
+            cost: |heat_loss| heat_loss as u64,
         }
     }
-}
-
-impl CrucibleLike for Crucible {
-    fn next(&self, grid: &Grid<u8>) -> Vec<Successor<Self, u64>> {
-        let mut next_nodes = vec![];
-        if self.forward_times < 3 {
-            if let Some(next) = grid.move_in_direction_if(&self.position, self.direction, |_| true)
-            {
-                let cost = grid[&next];
-                next_nodes.push(
-                    Self {
-                        position: next,
-                        direction: self.direction,
-                        forward_times: self.forward_times + 1,
-                    }
-                    .to_successor(cost as u64),
-                );
-            }
-        }
-        for direction in [self.direction.turn_left(), self.direction.turn_right()] {
-            if let Some(next) = grid.move_in_direction_if(&self.position, direction, |_| true) {
-                let cost = grid[&next];
-                next_nodes.push(
-                    Self {
-                        position: next,
-                        direction,
-                        forward_times: 1,
-                    }
-                    .to_successor(cost as u64),
-                );
-            }
+    pub fn ultra_crucible() -> Self {
+        Self {
+            min_run: 4,
+            max_run: 10,
+            ..Self::crucible()
         }
-
-        next_nodes
     }
-
-    fn position(&self) -> Point<usize> {
-        self.position
-    }
-
-    fn direction(&self) -> Direction {
-        self.direction
+    /// States index runs up to `max_run`, so a mover has to be able to go at least one block
+    /// and to reach `min_run` before it has to turn.
+    pub fn with_runs(self, min_run: u8, max_run: u8) -> anyhow::Result<Self> {
+        if max_run == 0 {
+            return Err(anyhow::anyhow!("the max run has to be at least 1"));
+        }
+        if min_run > max_run {
+            return Err(anyhow::anyhow!(
+                "the min run {} is longer than the max run {}",
+                min_run,
+                max_run
+            ));
+        }
+        Ok(Self {
+            min_run,
+            max_run,
+            ..self
+        })
     }
 }
 
-impl a_star::Node for Crucible {}
+/// The blocks a crucible went through after leaving the start, with the direction it
+/// entered each one in.
+#[derive(Debug, Clone)]
+struct HeatPath {
+    heat_loss: u64,
+    steps: Vec<(Point<usize>, Direction)>,
+}
 
-#[derive(Eq, PartialEq, Hash, Debug)]
-struct UltraCrucible(Crucible);
+struct CityMap {
+    grid: Grid<u8>,
+}
 
-impl a_star::Node for UltraCrucible {}
+/// A crucible on a block, with the direction it came in and how many blocks it has gone
+/// straight. A run of 0 is the start, where it can leave in any direction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct State {
+    position: Point<usize>,
+    direction: Direction,
+    run: u8,
+}
 
-impl CrucibleLike for UltraCrucible {
-    fn next(&self, grid: &Grid<u8>) -> Vec<Successor<Self, u64>> {
-        let mut next_nodes = vec![];
+/// Lowest heat loss to every state (`u64::MAX` if unreachable), and the state each one came from.
+struct Search {
+    heat_loss: Vec<u64>,
+    previous: Vec<Option<usize>>,
+}
 
-        let mut try_push_next = |direction: Direction, times: u8| {
-            let mut position = self.0.position;
-            let mut cost = 0u64;
-            for _ in 0..times {
-                position = if let Some(position) = position.move_in(direction) {
-                    position
-                } else {
-                    return;
-                };
-                cost += if let Some(cost) = grid.get(&position) {
-                    *cost as u64
-                } else {
-                    return;
+impl CityMap {
+    pub fn bottom_right(&self) -> Point<usize> {
+        Point {
+            x: self.grid.len_x() - 1,
+            y: self.grid.len_y() - 1,
+        }
+    }
+    pub fn block(&self, position: Point<usize>) -> anyhow::Result<Point<usize>> {
+        if position.x >= self.grid.len_x() || position.y >= self.grid.len_y() {
+            return Err(anyhow::anyhow!(
+                "block {} is outside the {}x{} map",
+                position,
+                self.grid.len_x(),
+                self.grid.len_y()
+            ));
+        }
+        Ok(position)
+    }
+    fn index(&self, mover: &Mover, state: &State) -> usize {
+        let tile = state.position.y * self.grid.len_x() + state.position.x;
+        let direction = Direction::all()
+            .iter()
+            .position(|&direction| direction == state.direction)
+            .unwrap();
+        (tile * 4 + direction) * (mover.max_run as usize + 1) + state.run as usize
+    }
+    fn state(&self, mover: &Mover, index: usize) -> State {
+        let runs = mover.max_run as usize + 1;
+        let tile = index / runs / 4;
+        State {
+            position: Point {
+                x: tile % self.grid.len_x(),
+                y: tile / self.grid.len_x(),
+            },
+            direction: Direction::all()[index / runs % 4],
+            run: (index % runs) as u8,
+        }
+    }
+    fn start(&self, mover: &Mover, position: Point<usize>) -> usize {
+        self.index(
+            mover,
+            &State {
+                position,
+                direction: Direction::Up,
+                run: 0,
+            },
+        )
+    }
+    /// Where the crucible can go next from `state`, and the heat it loses getting there.
+    fn moves(&self, mover: &Mover, state: State) -> impl Iterator<Item = (State, u64)> + '_ {
+        let directions: Vec<_> = if state.run == 0 {
+            Direction::all().to_vec()
+        } else {
+            let mut directions = vec![];
+            if state.run < mover.max_run {
+                directions.push(state.direction);
+            }
+            if state.run >= mover.min_run {
+                directions.extend([state.direction.turn_left(), state.direction.turn_right()]);
+                if mover.can_reverse {
+                    directions.push(state.direction.invert());
                 }
             }
-            next_nodes.push(
-                Self(Crucible {
+            directions
+        };
+        let cost = mover.cost;
+        directions.into_iter().filter_map(move |direction| {
+            let position = self
+                .grid
+                .move_in_direction_if(&state.position, direction, |_| true)?;
+            let run = if direction == state.direction && state.run > 0 {
+                state.run + 1
+            } else {
+                1
+            };
+            Some((
+                State {
                     position,
                     direction,
-                    forward_times: if self.0.direction == direction {
-                        self.0.forward_times + times
-                    } else {
-                        times
-                    },
-                })
-                .to_successor(cost),
-            );
+                    run,
+                },
+                cost(self.grid[&position]),
+            ))
+        })
+    }
+    /// Dijkstra from `start` over the crucible states, until `is_end` accepts one.
+    fn search(
+        &self,
+        mover: &Mover,
+        start: Point<usize>,
+        is_end: impl Fn(&State) -> bool,
+    ) -> (Search, Option<usize>) {
+        let state_count = self.grid.len_x() * self.grid.len_y() * 4 * (mover.max_run as usize + 1);
+        let mut search = Search {
+            heat_loss: vec![u64::MAX; state_count],
+            previous: vec![None; state_count],
         };
-
-        if (4..10).contains(&self.0.forward_times) {
-            try_push_next(self.0.direction, 1);
-        } else if self.0.forward_times == 0 {
-            try_push_next(self.0.direction, 4);
+        let mut queue = BucketQueue::default();
+        let start = self.start(mover, start);
+        search.heat_loss[start] = 0;
+        queue.push(0, start);
+        while let Some((heat_loss, current)) = queue.pop() {
+            if search.heat_loss[current] < heat_loss {
+                continue;
+            }
+            let state = self.state(mover, current);
+            if is_end(&state) {
+                return (search, Some(current));
+            }
+            for (next, cost) in self.moves(mover, state) {
+                let next = self.index(mover, &next);
+                let next_heat_loss = heat_loss + cost;
+                if next_heat_loss < search.heat_loss[next] {
+                    search.heat_loss[next] = next_heat_loss;
+                    search.previous[next] = Some(current);
+                    queue.push(next_heat_loss, next);
+                }
+            }
         }
-        try_push_next(self.0.direction.turn_left(), 4);
-        try_push_next(self.0.direction.turn_right(), 4);
-
-        next_nodes
+        (search, None)
     }
-
-    fn position(&self) -> Point<usize> {
-        self.0.position
+    /// The cheapest way from `start` to `end`, where the crucible has to be able to stop.
+    pub fn min_heat_loss(
+        &self,
+        mover: &Mover,
+        start: Point<usize>,
+        end: Point<usize>,
+    ) -> Option<HeatPath> {
+        let (search, end) = self.search(mover, start, |state| {
+            state.position == end && state.run >= mover.min_run
+        });
+        let end = end?;
+        let mut steps = vec![];
+        let mut current = end;
+        while let Some(previous) = search.previous[current] {
+            let state = self.state(mover, current);
+            steps.push((state.position, state.direction));
+            current = previous;
+        }
+        steps.reverse();
+        Some(HeatPath {
+            heat_loss: search.heat_loss[end],
+            steps,
+        })
     }
-
-    fn direction(&self) -> Direction {
-        self.0.direction
+    /// The lowest heat loss from `start` to every block the crucible can stop at.
+    pub fn heatmap(&self, mover: &Mover, start: Point<usize>) -> Grid<Option<u64>> {
+        let (search, _) = self.search(mover, start, |_| false);
+        let mut heatmap: Grid<Option<u64>> = Grid::new(self.grid.len_x(), self.grid.len_y());
+        for (index, &heat_loss) in search.heat_loss.iter().enumerate() {
+            let state = self.state(mover, index);
+            if heat_loss == u64::MAX || (state.run < mover.min_run && state.run > 0) {
+                continue;
+            }
+            let best = &mut heatmap[&state.position];
+            *best = Some(best.map_or(heat_loss, |best| best.min(heat_loss)));
+        }
+        heatmap
+    }
+    /// Up to `k` different ways from `start` to `end`, cheapest first. Every state gets settled
+    /// up to `k` times instead of once, so the `k`-th path through a state is still found.
+    pub fn cheapest_paths(
+        &self,
+        mover: &Mover,
+        start: Point<usize>,
+        end: Point<usize>,
+        k: usize,
+    ) -> Vec<HeatPath> {
+        // partial paths as (state, index of the partial path before it)
+        let mut partial: Vec<(usize, Option<usize>)> = vec![(self.start(mover, start), None)];
+        let mut settled: HashMap<usize, usize> = HashMap::new();
+        let mut queue = BucketQueue::default();
+        queue.push(0, 0);
+        let mut found = vec![];
+        while let Some((heat_loss, current)) = queue.pop() {
+            let (index, _) = partial[current];
+            let times = settled.entry(index).or_default();
+            if *times >= k {
+                continue;
+            }
+            *times += 1;
+            let state = self.state(mover, index);
+            if state.position == end && state.run >= mover.min_run {
+                let mut steps = vec![];
+                let mut node = current;
+                while let (index, Some(previous)) = partial[node] {
+                    let state = self.state(mover, index);
+                    steps.push((state.position, state.direction));
+                    node = previous;
+                }
+                steps.reverse();
+                found.push(HeatPath { heat_loss, steps });
+                if found.len() == k {
+                    break;
+                }
+            }
+            for (next, cost) in self.moves(mover, state) {
+                partial.push((self.index(mover, &next), Some(current)));
+                queue.push(heat_loss + cost, partial.len() - 1);
+            }
+        }
+        found
+    }
+    /// The map with the path drawn over it as arrows.
+    pub fn render_path(&self, path: &HeatPath) -> String {
+        let steps: HashMap<_, _> = path.steps.iter().copied().collect();
+        self.grid
+            .display_overriding(|point| {
+                steps
+                    .get(point)
+                    .map(|direction| direction.to_string().bright_blue())
+            })
+            .to_string()
     }
 }
 
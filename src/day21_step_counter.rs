@@ -1,6 +1,7 @@
 use crate::common::day_setup::{AppContext, Day};
 use crate::common::models::grid::GridLike;
 use crate::common::models::{Direction, Grid, Point};
+use crate::common::sequence::{self, Polynomial};
 use colored::Colorize;
 use std::collections::HashSet;
 
@@ -36,97 +37,145 @@ pub fn run(context: &AppContext) {
     );
     println!("part 1 visited in {} steps: {}", steps, end_positions.len());
 
-    if context.is_testing() {
-        println!("part 2 only works for full version");
-        return;
-    }
+    let steps = if context.is_testing() { 5000 } else { 26501365 };
     println!("part 2:");
-    let steps = 26501365;
-    // always looks diamond shape so we can probably solve geometrically
-    let sol = solve_geometrically(steps, &garden);
+    let sol = solve_by_sampling(steps, &garden, start_location).unwrap();
     println!("in {} steps: {}", steps, sol);
 }
 
-fn solve_geometrically(steps: u64, garden: &Garden) -> u64 {
-    assert!(
-        garden
-            .0
-            .iter()
-            .filter(|(point, _)| point.x == 0 || point.y == 0)
-            .all(|(_, &space)| space == Space::Plot),
-        "only works in the case that the horizontal and vertical axes are empty. Solution looks like a diamond"
-    );
-
-    let half_len = garden.half_len() as u64;
-    let grid_size = half_len * 2 + 1;
-
-    let n = (steps - half_len) / grid_size;
-    assert_eq!(
-        steps - half_len,
-        n * grid_size,
-        "method only works on exact multiples of steps"
-    );
-
-    // in the middle of the diamond
-    let same_parity_full = (n + 1).pow(2);
-    let other_parity_full = n.pow(2);
+/// Once the reachable area is wide enough, every time it grows by one more garden
+/// in each direction the count grows quadratically. Sample the counts for step numbers
+/// with the same remainder as `steps` and fit a polynomial through them.
+fn solve_by_sampling(steps: u64, garden: &Garden, start: Point<usize>) -> anyhow::Result<u64> {
+    const DEGREE: usize = 2;
+    const MAX_SAMPLES: u64 = 64;
+    let period = garden.0.len_x() as u64;
+    let offset = steps % period;
 
-    // at the edges of the diamond
-    let same_parity_outer_corners = n + 1; // these are outside the diamond and need to be subtracted
-    let other_parity_outer_corners = n; // these are inside the diamond
+    let sample_steps = |k: u64| offset + k * period;
+    let mut search = InfiniteSearch::new(garden, start);
+    let mut samples = vec![];
+    for k in 0.. {
+        anyhow::ensure!(
+            k < MAX_SAMPLES,
+            "no polynomial growth found in {} samples",
+            MAX_SAMPLES
+        );
+        if sample_steps(k) > steps {
+            anyhow::bail!("{} steps are reached before the growth settles", steps);
+        }
+        samples.push(search.reachable_in(sample_steps(k)) as i128);
+        // the growth counts as settled once the last few samples agree on the degree
+        let window = &samples[samples.len().saturating_sub(DEGREE + 3)..];
+        if window.len() == DEGREE + 3 && sequence::polynomial_degree(window) == Some(DEGREE) {
+            break;
+        }
+    }
 
-    let visited_plots = garden.get_all_visited_plots(Point {
-        x: half_len as usize,
-        y: half_len as usize,
-    });
-    let visited_corner_plots: HashSet<_> = garden
-        .filter_corner_points(visited_plots.iter().cloned())
+    let first = (samples.len() - DEGREE - 1) as i128;
+    let points: Vec<_> = (first..)
+        .zip(samples[first as usize..].iter().copied())
         .collect();
-
-    let visited_with_parity = |is_odd: bool, plots: &HashSet<Point<usize>>| {
-        plots
-            .iter()
-            .filter(|point| {
-                if is_odd {
-                    point.parity_xor()
-                } else {
-                    !point.parity_xor()
-                }
-            })
-            .count() as u64
-    };
-
-    same_parity_full * visited_with_parity(!steps.is_multiple_of(2), &visited_plots)
-        + other_parity_full * visited_with_parity(steps.is_multiple_of(2), &visited_plots)
-        + other_parity_outer_corners
-            * visited_with_parity(steps.is_multiple_of(2), &visited_corner_plots)
-        - same_parity_outer_corners
-            * visited_with_parity(!steps.is_multiple_of(2), &visited_corner_plots)
+    let polynomial = Polynomial::fit(&points)?;
+    log::debug!(
+        "reachable in {} + k * {} steps: {} (from k = {})",
+        offset,
+        period,
+        polynomial,
+        first
+    );
+    let result = polynomial.evaluate_integer(((steps - offset) / period) as i128)?;
+    Ok(u64::try_from(result)?)
 }
 
-struct Garden(Grid<Space>);
-impl Garden {
-    pub fn get_all_visited_plots(&self, start: Point<usize>) -> HashSet<Point<usize>> {
-        let mut visited = HashSet::new();
-        let mut positions = HashSet::from([start]);
-        loop {
-            for point in std::mem::take(&mut positions) {
-                if visited.contains(&point) {
-                    continue;
-                }
+/// Breadth first over the garden repeated infinitely in every direction, one step at a time
+/// as far as it is asked for. Only the last two layers are kept: on a grid, the plots next
+/// to one layer are all in the layer before or the one after it.
+struct InfiniteSearch<'a> {
+    garden: &'a Garden,
+    start: Point<i64>,
+    /// half the side of the square around `start` that `layer_marks` covers
+    radius: i64,
+    /// one more than the step count of the layer each plot was last seen in, 0 if never
+    layer_marks: Vec<u32>,
+    previous: Vec<Point<i64>>,
+    current: Vec<Point<i64>>,
+    /// for every number of steps, the plots reached in that many steps or fewer with the
+    /// same parity
+    reachable: Vec<usize>,
+}
 
-                for next in self.get_next_positions(&point) {
-                    positions.insert(next);
-                }
-                visited.insert(point);
+impl<'a> InfiniteSearch<'a> {
+    pub fn new(garden: &'a Garden, start: Point<usize>) -> Self {
+        let start = PointConverter(&start).into();
+        Self {
+            garden,
+            start,
+            radius: 0,
+            layer_marks: vec![1],
+            previous: vec![],
+            current: vec![start],
+            reachable: vec![1],
+        }
+    }
+    /// Plots the elf can end on after exactly `steps` steps.
+    pub fn reachable_in(&mut self, steps: u64) -> usize {
+        if steps as i64 > self.radius {
+            self.grow(steps as i64);
+        }
+        while self.reachable.len() <= steps as usize {
+            self.step();
+        }
+        self.reachable[steps as usize]
+    }
+    fn index(&self, point: &Point<i64>) -> usize {
+        let side = 2 * self.radius + 1;
+        ((point.y - self.start.y + self.radius) * side + point.x - self.start.x + self.radius)
+            as usize
+    }
+    /// Makes room for everything up to `radius` steps away, keeping the last two layers.
+    fn grow(&mut self, radius: i64) {
+        self.radius = radius;
+        self.layer_marks = vec![0; ((2 * radius + 1) * (2 * radius + 1)) as usize];
+        let steps = self.reachable.len() as u32;
+        for (layer, mark) in [(&self.previous, steps - 1), (&self.current, steps)] {
+            for point in layer.iter() {
+                let index = self.index(point);
+                self.layer_marks[index] = mark;
             }
-
-            if positions.is_empty() {
-                break;
+        }
+    }
+    fn step(&mut self) {
+        let steps = self.reachable.len() as u32;
+        let mut next = vec![];
+        for point in self.current.iter() {
+            for direction in Direction::all() {
+                let neighbour = point.move_in_direction_unchecked(direction);
+                let index = self.index(&neighbour);
+                let mark = self.layer_marks[index];
+                let seen = mark == steps + 1 || (mark != 0 && mark == steps - 1);
+                if !seen && self.garden.is_plot_wrapping(&neighbour) {
+                    self.layer_marks[index] = steps + 1;
+                    next.push(neighbour);
+                }
             }
         }
+        let same_parity = (steps as usize)
+            .checked_sub(2)
+            .map_or(0, |i| self.reachable[i]);
+        self.reachable.push(same_parity + next.len());
+        self.previous = std::mem::replace(&mut self.current, next);
+    }
+}
 
-        visited
+struct Garden(Grid<Space>);
+impl Garden {
+    fn is_plot_wrapping(&self, point: &Point<i64>) -> bool {
+        let point = Point {
+            x: point.x.rem_euclid(self.0.len_x() as i64) as usize,
+            y: point.y.rem_euclid(self.0.len_y() as i64) as usize,
+        };
+        self.0[&point] == Space::Plot
     }
     pub fn solve_for(&self, start: Point<usize>, steps: u64) -> HashSet<Point<usize>> {
         let mut positions = HashSet::from([start]);
@@ -168,27 +217,6 @@ impl Garden {
     pub fn new(grid: Grid<Space>) -> Self {
         Self(grid)
     }
-    pub fn half_len(&self) -> usize {
-        self.0.len_x() / 2
-    }
-    pub fn filter_corner_points<T: Iterator<Item = Point<usize>>>(
-        &self,
-        points: T,
-    ) -> impl Iterator<Item = Point<usize>> + use<'_, T> {
-        points.filter(|point| self.is_corner_point(&self.map_point_to_midpoint(point)))
-    }
-
-    fn map_point_to_midpoint(&self, point: &Point<usize>) -> Point<i64> {
-        Point {
-            x: point.x as i64 - self.half_len() as i64,
-            y: point.y as i64 - self.half_len() as i64,
-        }
-    }
-
-    fn is_corner_point(&self, point_centered_at_midpoint: &Point<i64>) -> bool {
-        point_centered_at_midpoint.x.abs() + point_centered_at_midpoint.y.abs()
-            > self.half_len() as i64
-    }
 }
 
 #[derive(Default, Copy, Clone, Eq, PartialEq, strum_macros::Display)]
@@ -240,13 +268,3 @@ impl<'a> From<PointConverter<'a>> for Point<i64> {
         }
     }
 }
-
-trait ParityXor {
-    fn parity_xor(&self) -> bool;
-}
-
-impl ParityXor for Point<usize> {
-    fn parity_xor(&self) -> bool {
-        (self.x % 2 == 1) != (self.y % 2 == 1)
-    }
-}
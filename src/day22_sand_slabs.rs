@@ -1,8 +1,7 @@
 use crate::common::day_setup::Day;
+use crate::common::models::{Axis, Direction3, Point3, Segment3};
+use anyhow::Context;
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fmt::Debug;
-use std::hash::Hash;
-use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 pub fn day() -> Day {
     Day::new(run).with_test_inputs(&["1,0,1~1,2,1
@@ -44,7 +43,7 @@ impl BrickTower {
                 (brick, letter as char)
             })
             .collect();
-        bricks.sort_by(|(a, _), (b, _)| a.position.z.cmp(&b.position.z));
+        bricks.sort_by_key(|(brick, _)| brick.position.z);
 
         for (brick, name) in bricks {
             tower.settle_brick(brick, name);
@@ -91,7 +90,7 @@ impl BrickTower {
 
         brick.position = brick.position.translate(
             translate_size,
-            Direction3D {
+            Direction3 {
                 axis: Axis::Z,
                 negative: true,
             },
@@ -145,7 +144,7 @@ impl BrickTower {
         };
         let pop_lowest_z = |set: &mut HashSet<usize>| -> Option<usize> {
             let mut vec: Vec<_> = set.iter().copied().collect();
-            vec.sort_unstable_by(|&a, &b| get_z(b).cmp(&get_z(a)));
+            vec.sort_unstable_by_key(|&i| std::cmp::Reverse(get_z(i)));
             vec.pop().inspect(|val| {
                 set.remove(val);
             })
@@ -187,13 +186,13 @@ struct BrickStatus {
 }
 
 struct Brick {
-    position: Coord,
+    position: Point3<u32>,
     size: u32,
-    extend_direction: Direction3D,
+    extend_direction: Direction3,
 }
 
 impl Brick {
-    pub fn new(position: Coord, size: u32, extend_direction: Direction3D) -> Self {
+    pub fn new(position: Point3<u32>, size: u32, extend_direction: Direction3) -> Self {
         if extend_direction.negative && extend_direction.axis == Axis::Z {
             panic!("invalid state - brick z should be lowest point");
         }
@@ -203,15 +202,14 @@ impl Brick {
             extend_direction,
         }
     }
-    pub fn get_highest_points(&self) -> Vec<Coord> {
+    pub fn get_highest_points(&self) -> Vec<Point3<u32>> {
         if self.extend_direction.axis == Axis::Z {
             if self.extend_direction.negative {
                 unreachable!()
-                //vec![self.position.clone()]
+                //vec![self.position]
             } else {
                 vec![
                     self.position
-                        .clone()
                         .translate(self.size - 1, self.extend_direction),
                 ]
             }
@@ -219,89 +217,29 @@ impl Brick {
             self.get_all_points()
         }
     }
-    pub fn get_lowest_points(&self) -> Vec<Coord> {
+    pub fn get_lowest_points(&self) -> Vec<Point3<u32>> {
         if self.extend_direction.axis == Axis::Z {
             if self.extend_direction.negative {
                 unreachable!()
-                //vec![self.position.clone().translate(self.size, self.extend_direction)]
+                //vec![self.position.translate(self.size, self.extend_direction)]
             } else {
-                vec![self.position.clone()]
+                vec![self.position]
             }
         } else {
             self.get_all_points()
         }
     }
-    fn get_all_points(&self) -> Vec<Coord> {
+    fn get_all_points(&self) -> Vec<Point3<u32>> {
         let mut result = Vec::with_capacity(self.size as usize);
-        result.push(self.position.clone());
+        result.push(self.position);
         for _ in 1..self.size {
-            result.push(
-                result
-                    .last()
-                    .unwrap()
-                    .clone()
-                    .translate(1, self.extend_direction),
-            );
+            result.push(result.last().unwrap().translate(1, self.extend_direction));
         }
         assert_eq!(self.size as usize, result.len());
         result
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct Coord {
-    x: u32,
-    y: u32,
-    z: u32,
-}
-
-impl Coord {
-    pub fn translate(mut self, size: u32, extend_direction: Direction3D) -> Self {
-        if extend_direction.negative {
-            self[extend_direction.axis] -= size;
-        } else {
-            self[extend_direction.axis] += size;
-        }
-        self
-    }
-}
-
-#[derive(Copy, Debug, Clone, Eq, PartialEq, Hash)]
-struct Direction3D {
-    axis: Axis,
-    negative: bool,
-}
-
-#[derive(Default, Copy, Debug, Clone, Eq, PartialEq, Hash)]
-enum Axis {
-    #[default]
-    X,
-    Y,
-    Z,
-}
-
-impl Index<Axis> for Coord {
-    type Output = u32;
-
-    fn index(&self, index: Axis) -> &Self::Output {
-        match index {
-            Axis::X => &self.x,
-            Axis::Y => &self.y,
-            Axis::Z => &self.z,
-        }
-    }
-}
-
-impl IndexMut<Axis> for Coord {
-    fn index_mut(&mut self, index: Axis) -> &mut Self::Output {
-        match index {
-            Axis::X => &mut self.x,
-            Axis::Y => &mut self.y,
-            Axis::Z => &mut self.z,
-        }
-    }
-}
-
 impl FromStr for Brick {
     type Err = anyhow::Error;
 
@@ -309,41 +247,16 @@ impl FromStr for Brick {
         let [left, right] = s.trim().split("~").collect::<Vec<_>>()[..] else {
             return Err(anyhow::anyhow!("not a brick format: '{}'", s));
         };
-        let mut left: Coord = left.parse()?;
-        let mut right: Coord = right.parse()?;
-        if right.z < left.z {
-            std::mem::swap(&mut left, &mut right);
-        }
-
-        let mut axes = [Axis::X, Axis::Y, Axis::Z]
-            .iter()
-            .filter(|&&axis| left[axis] != right[axis]);
-        let axis = axes.next().copied().unwrap_or_default();
-        if axes.next().is_some() {
-            return Err(anyhow::anyhow!(
-                "block extends in multiple directions... : {}",
-                s
-            ));
-        }
-
-        let size = left[axis].abs_diff(right[axis]) + 1;
-        let negative = left[axis] > right[axis];
-
-        Ok(Self::new(left, size, Direction3D { axis, negative }))
-    }
-}
-
-impl FromStr for Coord {
-    type Err = anyhow::Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let [x, y, z] = s.trim().split(",").collect::<Vec<_>>()[..] else {
-            return Err(anyhow::anyhow!("not a coord format: '{}'", s));
-        };
-        Ok(Self {
-            x: x.parse()?,
-            y: y.parse()?,
-            z: z.parse()?,
-        })
+        let segment = Segment3::new(left.parse()?, right.parse()?)
+            .with_context(|| format!("block extends in multiple directions... : {}", s))?;
+
+        Ok(Self::new(
+            *segment.start(),
+            segment.len() + 1,
+            Direction3 {
+                axis: segment.axis(),
+                negative: false,
+            },
+        ))
     }
 }
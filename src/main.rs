@@ -63,6 +63,7 @@ fn main() {
             .unwrap_or_else(|_| panic!("Failed to read input file input/{:02}.txt", day))
             .into()
     }));
+    context.set_args(std::env::args().skip(2).collect());
     let run = days[day - 1]();
 
     println!("Running day {}\n", day);